@@ -0,0 +1,240 @@
+//! # Auth
+//!
+//! Obtain a consumer key: ask OVH for one restricted to some access
+//! rules, have the account owner validate it at the given url, then
+//! wait until it is validated.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//! use ovh::auth::AccessRule;
+//! use ovh::task::WaitOptions;
+//!
+//! let client = OVHClient::new();
+//! let pending = client.request_consumer_key(&AccessRule::read_only(), None).unwrap();
+//! println!("Validate at {}", pending.validation_url);
+//! let credential = client.wait_for_validation(&pending, &WaitOptions::default()).unwrap();
+//! credential.save_to_file("Config.toml").unwrap();
+//! ```
+//!
+extern crate serde_json;
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use client::OVHClient;
+use config::Credential;
+use error::Error;
+use secret::Secret;
+use task::{poll, WaitOptions};
+
+/// Method and path (`*` being a wildcard) a consumer key may call.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct AccessRule {
+    pub method: String,
+    pub path: String,
+}
+
+impl AccessRule {
+    pub fn new(method: &str, path: &str) -> AccessRule {
+        AccessRule {
+            method: method.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    /// `GET` on every path.
+    pub fn read_only() -> Vec<AccessRule> {
+        vec![AccessRule::new("GET", "/*")]
+    }
+
+    /// Every method on every path.
+    pub fn full() -> Vec<AccessRule> {
+        ["GET", "POST", "PUT", "DELETE"]
+            .iter()
+            .map(|method| AccessRule::new(method, "/*"))
+            .collect()
+    }
+
+    /// Whether this rule allows `method` calls to `path`, `*` matching
+    /// any characters. The query string of `path` is ignored.
+    pub fn matches(&self, method: &str, path: &str) -> bool {
+        let path = path.split('?').next().unwrap_or("");
+        self.method == method && glob(self.path.as_bytes(), path.as_bytes())
+    }
+
+    /// Read rules from a JSON file shaped like the `accessRules` of the
+    /// API, e.g. `[{"method": "GET", "path": "/domain/*"}]`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<AccessRule>> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        serde_json::from_str(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((&b'*', rest)) => (0..text.len() + 1).any(|skip| glob(rest, &text[skip..])),
+        Some((c, rest)) => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
+
+#[derive(Serialize)]
+struct CredentialRequest<'a> {
+    #[serde(rename = "accessRules")]
+    access_rules: &'a [AccessRule],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    redirection: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct CredentialResponse {
+    #[serde(rename = "validationUrl")]
+    validation_url: String,
+    #[serde(rename = "consumerKey")]
+    consumer_key: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct CurrentCredential {
+    status: String,
+}
+
+/// A consumer key waiting for validation.
+#[derive(Debug,Clone)]
+pub struct PendingCredential {
+    /// Where the account owner validates the consumer key.
+    pub validation_url: String,
+    pub consumer_key: Secret,
+    /// `pendingValidation` when just requested.
+    pub state: String,
+}
+
+/// Status of a consumer key the API rejected with `message`, if known:
+/// keys are rejected until validated, and for good once refused or expired.
+fn rejected_status(message: &str) -> Option<&'static str> {
+    let lowercase = message.to_lowercase();
+    if lowercase.contains("refused") {
+        Some("refused")
+    } else if lowercase.contains("expired") {
+        Some("expired")
+    } else if message == "This credential is not valid" || message == "Invalid credential" {
+        Some("pendingValidation")
+    } else {
+        None
+    }
+}
+
+impl OVHClient {
+    /// Ask OVH for a new consumer key restricted to `rules`.
+    ///
+    /// Once validated, the account owner is sent to `redirection` if given.
+    pub fn request_consumer_key(&self,
+                                rules: &[AccessRule],
+                                redirection: Option<&str>)
+                                -> Result<PendingCredential, Error> {
+        let body = serde_json::to_string(&CredentialRequest {
+            access_rules: rules,
+            redirection: redirection,
+        })?;
+        let response = self.execute_unauthenticated("POST", "/auth/credential", &body)?
+            .error_for_status()?;
+        let created: CredentialResponse = serde_json::from_str(&response.body)?;
+        Ok(PendingCredential {
+            validation_url: created.validation_url,
+            consumer_key: Secret::new(created.consumer_key),
+            state: created.state,
+        })
+    }
+
+    /// Poll `/auth/currentCredential` until `pending` is validated, and
+    /// return the client's `Credential` with its consumer key.
+    ///
+    /// Fails with `Error::Auth` as soon as the API reports the consumer
+    /// key refused or expired, or if it is still not validated after
+    /// `options.timeout`. Other errors, e.g. a 403 for an unknown
+    /// application key, are returned at once.
+    pub fn wait_for_validation(&self,
+                               pending: &PendingCredential,
+                               options: &WaitOptions)
+                               -> Result<Credential, Error> {
+        let consumer_key = pending.consumer_key.expose();
+        let credential = poll(options, || {
+            let response = self.execute_as(consumer_key, "GET", "/auth/currentCredential", "")?;
+            let status = match response.error_for_status() {
+                Ok(response) => serde_json::from_str::<CurrentCredential>(&response.body)?.status,
+                Err(Error::Api { status, message }) => {
+                    match rejected_status(&message) {
+                        Some(rejected) if status == 401 || status == 403 => rejected.to_string(),
+                        _ => {
+                            return Err(Error::Api {
+                                status: status,
+                                message: message,
+                            })
+                        }
+                    }
+                }
+                Err(err) => return Err(err),
+            };
+            debug!("Consumer key status: {}", status);
+
+            match status.as_str() {
+                "validated" => {
+                    let mut credential = self.current_credential();
+                    credential.consumer_key = pending.consumer_key.clone();
+                    Ok(Some(credential))
+                }
+                "pendingValidation" => Ok(None),
+                other => Err(Error::Auth(format!("consumer key is {}", other))),
+            }
+        })?;
+        credential.ok_or_else(|| {
+            Error::Auth(format!("consumer key not validated after {:?}", options.timeout))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rejected_status, AccessRule, CredentialRequest};
+    use super::serde_json;
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(vec![AccessRule::new("GET", "/*")], AccessRule::read_only());
+        assert_eq!(4, AccessRule::full().len());
+        assert_eq!(true, AccessRule::full().contains(&AccessRule::new("DELETE", "/*")));
+    }
+
+    #[test]
+    fn test_matches() {
+        let rule = AccessRule::new("GET", "/domain/zone/*/record");
+        assert_eq!(true, rule.matches("GET", "/domain/zone/example.com/record?fieldType=A"));
+        assert_eq!(false, rule.matches("POST", "/domain/zone/example.com/record"));
+        assert_eq!(false, rule.matches("GET", "/domain/zone/example.com/refresh"));
+        assert_eq!(true, AccessRule::new("GET", "/*").matches("GET", "/me/bill"));
+        assert_eq!(false, AccessRule::new("GET", "/me").matches("GET", "/me/bill"));
+    }
+
+    #[test]
+    fn test_credential_request() {
+        let rules = vec![AccessRule::new("GET", "/me")];
+        let body = serde_json::to_string(&CredentialRequest {
+                access_rules: &rules,
+                redirection: None,
+            })
+            .unwrap();
+        assert_eq!(r#"{"accessRules":[{"method":"GET","path":"/me"}]}"#, body);
+    }
+
+    #[test]
+    fn test_rejected_status() {
+        assert_eq!(Some("pendingValidation"), rejected_status("This credential is not valid"));
+        assert_eq!(Some("refused"), rejected_status("This credential has been refused"));
+        assert_eq!(Some("expired"), rejected_status("This credential is expired"));
+        assert_eq!(None, rejected_status("This call has not been granted"));
+    }
+}
@@ -0,0 +1,157 @@
+//! # ovh-codegen
+//!
+//! Generate typed Rust bindings from OVH API schemas, see `ovh::codegen`.
+//!
+//! ```text
+//! ovh-codegen --out src/api domain me dedicated/server
+//! ovh-codegen --out src/api schemas/domain.json
+//! ```
+//!
+extern crate ovh;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+use ovh::{Credential, OVHClient};
+use ovh::codegen::{self, Generator};
+use ovh::schema::Schema;
+
+const USAGE: &'static str = "Usage: ovh-codegen [options] <schema>...
+
+Each schema is either a .json file or the name of an API to download,
+e.g. domain or dedicated/server.
+
+Options:
+    -o, --out DIR          Write one module per schema and a mod.rs to DIR,
+                           instead of printing the bindings
+    -e, --endpoint NAME    Endpoint to download schemas from [default: ovh-eu]
+    --crate PATH           Path of the ovh crate in the generated code
+                           [default: ::ovh]
+    -h, --help             Print this help";
+
+#[derive(Debug,PartialEq)]
+struct Options {
+    out: Option<String>,
+    endpoint: String,
+    krate: String,
+    schemas: Vec<String>,
+    help: bool,
+}
+
+/// Parse arguments, without the program name.
+fn parse_args<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options {
+        out: None,
+        endpoint: "ovh-eu".to_string(),
+        krate: "::ovh".to_string(),
+        schemas: Vec::new(),
+        help: false,
+    };
+
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next().ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-o" | "--out" => options.out = Some(value(&arg)?),
+            "-e" | "--endpoint" => options.endpoint = value(&arg)?,
+            "--crate" => options.krate = value(&arg)?,
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            _ => options.schemas.push(arg),
+        }
+    }
+    if options.schemas.is_empty() && !options.help {
+        return Err("Missing schema".to_string());
+    }
+    Ok(options)
+}
+
+/// Load a schema file, or download the schema of an API.
+fn load_schema(client: &OVHClient, schema: &str) -> Result<Schema, String> {
+    if schema.ends_with(".json") {
+        Schema::load(schema).map_err(|err| format!("Cannot read schema {}: {}", schema, err))
+    } else {
+        client.fetch_schema(schema)
+            .map_err(|err| format!("Cannot download schema of {}: {}", schema, err))
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<(), String> {
+    File::create(path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|err| format!("Cannot write {}: {}", path.display(), err))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    // Schemas are public, no consumer key needed
+    let client = OVHClient::with_credential(Credential::new_with_application(&options.endpoint,
+                                                                             "",
+                                                                             ""));
+    let generator = Generator { krate: options.krate.clone() };
+
+    let mut modules = Vec::new();
+    for name in &options.schemas {
+        let schema = load_schema(&client, name)?;
+        let code = generator.generate(&schema);
+        match options.out {
+            Some(ref out) => {
+                fs::create_dir_all(out).map_err(|err| format!("Cannot create {}: {}", out, err))?;
+                let module = codegen::module_name(&schema);
+                write_file(&Path::new(out).join(format!("{}.rs", module)), &code)?;
+                modules.push(module);
+            }
+            None => print!("{}", code),
+        }
+    }
+
+    if let Some(ref out) = options.out {
+        let mut mod_rs = String::from("// Generated by ovh-codegen, do not edit.\n\n");
+        for module in &modules {
+            mod_rs.push_str(&format!("pub mod {};\n", module));
+        }
+        write_file(&Path::new(out).join("mod.rs"), &mod_rs)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+    if let Err(err) = run(&options) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_args;
+
+    fn parse(args: &[&str]) -> Result<super::Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse(&["-o", "src/api", "domain", "schemas/me.json"]).unwrap();
+        assert_eq!(Some("src/api".to_string()), options.out);
+        assert_eq!(vec!["domain".to_string(), "schemas/me.json".to_string()], options.schemas);
+        assert_eq!("::ovh", options.krate);
+        assert_eq!(true, parse(&["-o", "src/api"]).is_err());
+        assert_eq!(true, parse(&["--verbose", "domain"]).is_err());
+    }
+}
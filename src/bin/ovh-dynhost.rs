@@ -0,0 +1,167 @@
+//! # ovh-dynhost
+//!
+//! Keep a DynHost record pointing to the public IP of the host it runs
+//! on, see `ovh::dynhost`.
+//!
+//! ```text
+//! ovh-dynhost --zone example.com --sub-domain office --interval 300
+//! ```
+//!
+extern crate env_logger;
+extern crate ovh;
+
+use std::env;
+use std::process;
+use std::time::Duration;
+
+use ovh::{Credential, OVHClient};
+use ovh::dynhost::{HttpIpSource, Updater, DEFAULT_IP_URL};
+
+const USAGE: &'static str = "Usage: ovh-dynhost [options] --zone ZONE --sub-domain NAME
+
+Update the DynHost record of NAME.ZONE whenever the public IP changes.
+
+Options:
+    -z, --zone ZONE          Zone of the record, e.g. example.com
+    -s, --sub-domain NAME    Sub-domain of the record, e.g. office
+    -i, --interval SECONDS   Delay between two checks [default: 300]
+    -u, --ip-url URL         Service answering the public IP in plain text
+                             [default: https://api.ipify.org]
+    -1, --once               Check once and exit, e.g. from cron
+    -c, --config PATH        Config file [env: OVH_CONFIG, default: Config.toml]
+    -p, --profile NAME       Endpoint section of the config file, e.g. ovh-ca
+                             [env: OVH_PROFILE, default: the [default] endpoint]
+    -h, --help               Print this help
+
+Set RUST_LOG=info to log updates.";
+
+#[derive(Debug,PartialEq)]
+struct Options {
+    zone: String,
+    sub_domain: String,
+    interval: Duration,
+    ip_url: String,
+    once: bool,
+    config: String,
+    profile: Option<String>,
+    help: bool,
+}
+
+/// Parse arguments, without the program name, falling back to `config`
+/// and `profile` (from `OVH_CONFIG` and `OVH_PROFILE`) for the options not given.
+fn parse_args<I: Iterator<Item = String>>(args: I,
+                                          config: Option<String>,
+                                          profile: Option<String>)
+                                          -> Result<Options, String> {
+    let mut options = Options {
+        zone: String::new(),
+        sub_domain: String::new(),
+        interval: Duration::from_secs(300),
+        ip_url: DEFAULT_IP_URL.to_string(),
+        once: false,
+        config: config.unwrap_or_else(|| "Config.toml".to_string()),
+        profile: profile,
+        help: false,
+    };
+
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next().ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-z" | "--zone" => options.zone = value(&arg)?,
+            "-s" | "--sub-domain" => options.sub_domain = value(&arg)?,
+            "-i" | "--interval" => {
+                let interval = value(&arg)?;
+                let seconds = interval.parse::<u64>()
+                    .ok()
+                    .filter(|seconds| *seconds > 0)
+                    .ok_or_else(|| format!("Invalid interval {}", interval))?;
+                options.interval = Duration::from_secs(seconds);
+            }
+            "-u" | "--ip-url" => options.ip_url = value(&arg)?,
+            "-1" | "--once" => options.once = true,
+            "-c" | "--config" => options.config = value(&arg)?,
+            "-p" | "--profile" => options.profile = Some(value(&arg)?),
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+    if !options.help && (options.zone.is_empty() || options.sub_domain.is_empty()) {
+        return Err("Missing --zone or --sub-domain".to_string());
+    }
+    Ok(options)
+}
+
+fn main() {
+    let _ = env_logger::init();
+
+    let options = match parse_args(env::args().skip(1),
+                                   env::var("OVH_CONFIG").ok(),
+                                   env::var("OVH_PROFILE").ok()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let loaded = match options.profile {
+        Some(ref profile) => Credential::load_profile(&options.config, profile),
+        None => Credential::load_from_file(&options.config),
+    };
+    let credential = match loaded {
+        Ok(credential) => credential,
+        Err(err) => {
+            eprintln!("Cannot load credentials from {}: {}", options.config, err);
+            process::exit(1);
+        }
+    };
+    let client = OVHClient::with_credential(credential);
+    let mut source = HttpIpSource::new(client.transport());
+    source.url = options.ip_url.clone();
+    let mut updater = Updater::new(client, &options.zone, &options.sub_domain, source);
+
+    if !options.once {
+        updater.run(options.interval);
+        return;
+    }
+    match updater.check() {
+        Ok(Some(ip)) => println!("{}.{} updated to {}", options.sub_domain, options.zone, ip),
+        Ok(None) => println!("{}.{} is up to date", options.sub_domain, options.zone),
+        Err(err) => {
+            eprintln!("Cannot update {}.{}: {}", options.sub_domain, options.zone, err);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::parse_args;
+
+    fn parse(args: &[&str]) -> Result<super::Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()), None, None)
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse(&["-z", "example.com", "-s", "office", "-i", "60", "--once"]).unwrap();
+        assert_eq!("example.com", options.zone);
+        assert_eq!("office", options.sub_domain);
+        assert_eq!(Duration::from_secs(60), options.interval);
+        assert_eq!(true, options.once);
+        assert_eq!("https://api.ipify.org", options.ip_url);
+
+        assert_eq!(true, parse(&["-z", "example.com"]).is_err());
+        assert_eq!(true, parse(&["-z", "example.com", "-s", "office", "-i", "0"]).is_err());
+        assert_eq!(true, parse(&["--help"]).unwrap().help);
+    }
+}
@@ -0,0 +1,164 @@
+//! # ovh-proxy
+//!
+//! Local proxy signing calls to OVH's API, for tools written in
+//! languages without an OVH SDK.
+//!
+//! ```text
+//! ovh-proxy --allow "GET /domain/*" --allow "POST /domain/zone/*/refresh"
+//! curl http://127.0.0.1:8017/1.0/domain/zone
+//! ```
+//!
+extern crate env_logger;
+extern crate ovh;
+
+use std::env;
+use std::process;
+
+use ovh::{Credential, OVHClient};
+use ovh::auth::AccessRule;
+use ovh::middleware::Logger;
+use ovh::proxy::Proxy;
+
+const USAGE: &'static str = "Usage: ovh-proxy [options]
+
+Sign and forward calls received under /1.0/ to OVH's API.
+
+Options:
+    -l, --listen ADDR     Address to listen on [default: 127.0.0.1:8017]
+    -c, --config PATH     Config file [env: OVH_CONFIG, default: Config.toml]
+    -p, --profile NAME    Endpoint section of the config file, e.g. ovh-ca
+                          [env: OVH_PROFILE, default: the [default] endpoint]
+    -a, --allow RULE      Allow calls matching RULE, e.g. \"GET /domain/*\",
+                          can be repeated
+    -r, --rules @FILE     Allow calls matching the JSON access rules of FILE
+    -h, --help            Print this help
+
+Nothing is allowed unless given by --allow or --rules.
+Set RUST_LOG=info to log calls.";
+
+#[derive(Debug,PartialEq)]
+struct Options {
+    listen: String,
+    config: String,
+    profile: Option<String>,
+    allow: Vec<AccessRule>,
+    help: bool,
+}
+
+/// Parse a `METHOD /path` rule.
+fn parse_rule(rule: &str) -> Result<AccessRule, String> {
+    let mut parts = rule.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(path), None) if path.starts_with('/') => {
+            Ok(AccessRule::new(&method.to_uppercase(), path))
+        }
+        _ => Err(format!("Invalid rule {:?}, expected e.g. \"GET /domain/*\"", rule)),
+    }
+}
+
+/// Parse arguments, without the program name, falling back to `config`
+/// and `profile` (from `OVH_CONFIG` and `OVH_PROFILE`) for the options not given.
+fn parse_args<I: Iterator<Item = String>>(args: I,
+                                          config: Option<String>,
+                                          profile: Option<String>)
+                                          -> Result<Options, String> {
+    let mut options = Options {
+        listen: "127.0.0.1:8017".to_string(),
+        config: config.unwrap_or_else(|| "Config.toml".to_string()),
+        profile: profile,
+        allow: Vec::new(),
+        help: false,
+    };
+
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next().ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => options.help = true,
+            "-l" | "--listen" => options.listen = value(&arg)?,
+            "-c" | "--config" => options.config = value(&arg)?,
+            "-p" | "--profile" => options.profile = Some(value(&arg)?),
+            "-a" | "--allow" => options.allow.push(parse_rule(&value(&arg)?)?),
+            "-r" | "--rules" => {
+                let rules = value(&arg)?;
+                if !rules.starts_with('@') {
+                    return Err(format!("Expected @FILE for {}", arg));
+                }
+                let loaded = AccessRule::from_file(&rules[1..])
+                    .map_err(|err| format!("Cannot read rules {}: {}", rules, err))?;
+                options.allow.extend(loaded);
+            }
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+    if options.allow.is_empty() && !options.help {
+        return Err("No call allowed, use --allow or --rules".to_string());
+    }
+    Ok(options)
+}
+
+fn main() {
+    let _ = env_logger::init();
+
+    let options = match parse_args(env::args().skip(1),
+                                   env::var("OVH_CONFIG").ok(),
+                                   env::var("OVH_PROFILE").ok()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let loaded = match options.profile {
+        Some(ref profile) => Credential::load_profile(&options.config, profile),
+        None => Credential::load_from_file(&options.config),
+    };
+    let credential = match loaded {
+        Ok(credential) => credential,
+        Err(err) => {
+            eprintln!("Cannot load credentials from {}: {}", options.config, err);
+            process::exit(1);
+        }
+    };
+    let mut client = OVHClient::with_credential(credential);
+    client.add_middleware(Logger { bodies: false });
+
+    let proxy = Proxy::new(client, options.allow);
+    if let Err(err) = proxy.serve(options.listen.as_str()) {
+        eprintln!("Cannot listen on {}: {}", options.listen, err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_args, parse_rule};
+    use ovh::auth::AccessRule;
+
+    fn parse(args: &[&str]) -> Result<super::Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()), None, None)
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!(AccessRule::new("GET", "/domain/*"), parse_rule("get /domain/*").unwrap());
+        assert_eq!(true, parse_rule("GET").is_err());
+        assert_eq!(true, parse_rule("GET domain").is_err());
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse(&["-l", "127.0.0.1:9000", "--allow", "GET /me"]).unwrap();
+        assert_eq!("127.0.0.1:9000", options.listen);
+        assert_eq!(vec![AccessRule::new("GET", "/me")], options.allow);
+        assert_eq!(true, parse(&[]).is_err());
+        assert_eq!(true, parse(&["--rules", "rules.json"]).is_err());
+    }
+}
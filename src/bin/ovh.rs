@@ -0,0 +1,529 @@
+//! # ovh
+//!
+//! Command line client for OVH's API, signing calls with the
+//! credentials of a `Config.toml` file.
+//!
+//! ```text
+//! ovh get /me
+//! ovh --profile ovh-ca post /order/cart --data @cart.json
+//! ovh auth login --rules full
+//! ovh api search "dns record" domain
+//! ```
+//!
+extern crate ovh;
+extern crate serde_json;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::process;
+use std::time::Duration;
+
+use ovh::{config, Credential, OVHClient};
+use ovh::auth::AccessRule;
+use ovh::client::Response;
+use ovh::explorer::{self, Endpoint};
+use ovh::task::WaitOptions;
+
+const USAGE: &'static str = "Usage: ovh [options] <command>
+
+Commands:
+    get <path>                  Call the API with GET
+    post <path> [--data DATA]   Call the API with POST
+    put <path> [--data DATA]    Call the API with PUT
+    delete <path>               Call the API with DELETE
+    auth login [--rules RULES]  Get a new consumer key, validate it in a
+                                browser, then save it to the config file
+    api list                    List the API families, e.g. /domain
+    api search <words> [api]... Search operations of the given APIs, or of
+                                all of them, e.g. api search \"dns record\" domain
+    api show <method> <path>    Show the parameters and return type of an
+                                operation, e.g. api show get /domain/zone
+
+Options:
+    -c, --config PATH     Config file [env: OVH_CONFIG, default: Config.toml]
+    -p, --profile NAME    Endpoint section of the config file, e.g. ovh-ca
+                          [env: OVH_PROFILE, default: the [default] endpoint]
+    -d, --data DATA       JSON body, @FILE to read it from a file, @- from stdin
+    -n, --dry-run         Sign but do not send POST, PUT and DELETE calls
+    -r, --rules RULES     Access rules of the consumer key: read-only, full,
+                          or @FILE of JSON rules [default: read-only]
+    --redirection URL     Where to go once the consumer key is validated
+    -h, --help            Print this help
+
+Exit status is 0 on 2xx and 3xx, 4 on 4xx, 5 on 5xx, 1 on other errors
+and 2 on usage errors.";
+
+#[derive(Debug,PartialEq)]
+enum Command {
+    Call {
+        method: String,
+        path: String,
+        data: Option<String>,
+    },
+    Login {
+        rules: String,
+        redirection: Option<String>,
+    },
+    ApiList,
+    ApiSearch {
+        keywords: String,
+        apis: Vec<String>,
+    },
+    ApiShow {
+        method: String,
+        path: String,
+    },
+    Help,
+}
+
+#[derive(Debug,PartialEq)]
+struct Options {
+    config: String,
+    profile: Option<String>,
+    dry_run: bool,
+    command: Command,
+}
+
+/// Parse arguments, without the program name, falling back to `config`
+/// and `profile` (from `OVH_CONFIG` and `OVH_PROFILE`) for the options not given.
+fn parse_args<I: Iterator<Item = String>>(args: I,
+                                          config: Option<String>,
+                                          profile: Option<String>)
+                                          -> Result<Options, String> {
+    let mut config = config.unwrap_or_else(|| "Config.toml".to_string());
+    let mut profile = profile;
+    let mut dry_run = false;
+    let mut data = None;
+    let mut rules = None;
+    let mut redirection = None;
+    let mut positional = Vec::new();
+
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next().ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => positional.insert(0, "help".to_string()),
+            "-c" | "--config" => config = value(&arg)?,
+            "-p" | "--profile" => profile = Some(value(&arg)?),
+            "-d" | "--data" => data = Some(value(&arg)?),
+            "-n" | "--dry-run" => dry_run = true,
+            "-r" | "--rules" => rules = Some(value(&arg)?),
+            "--redirection" => redirection = Some(value(&arg)?),
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("Unknown option {}", arg))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let command = match positional.first().map(|command| command.as_str()) {
+        None | Some("help") => Command::Help,
+        Some("auth") => {
+            match positional.get(1).map(|command| command.as_str()) {
+                Some("login") if positional.len() == 2 => {
+                    if data.is_some() {
+                        return Err("auth login does not take --data".to_string());
+                    }
+                    Command::Login {
+                        rules: rules.unwrap_or_else(|| "read-only".to_string()),
+                        redirection: redirection,
+                    }
+                }
+                Some("login") => return Err(format!("Unexpected argument {}", positional[2])),
+                Some(command) => return Err(format!("Unknown command auth {}", command)),
+                None => return Err("Missing command for auth".to_string()),
+            }
+        }
+        Some("api") => {
+            if data.is_some() || rules.is_some() || redirection.is_some() {
+                return Err("api does not take --data, --rules nor --redirection".to_string());
+            }
+            let (command, expected) = match positional.get(1).map(|command| command.as_str()) {
+                Some("list") => (Command::ApiList, 2),
+                Some("search") => {
+                    match positional.get(2) {
+                        Some(keywords) => {
+                            (Command::ApiSearch {
+                                 keywords: keywords.clone(),
+                                 apis: positional[3..].to_vec(),
+                             },
+                             positional.len())
+                        }
+                        None => return Err("Missing words for api search".to_string()),
+                    }
+                }
+                Some("show") => {
+                    match (positional.get(2), positional.get(3)) {
+                        (Some(method), Some(path)) => {
+                            (Command::ApiShow {
+                                 method: method.to_uppercase(),
+                                 path: with_slash(path),
+                             },
+                             4)
+                        }
+                        _ => return Err("Missing method or path for api show".to_string()),
+                    }
+                }
+                Some(command) => return Err(format!("Unknown command api {}", command)),
+                None => return Err("Missing command for api".to_string()),
+            };
+            if positional.len() > expected {
+                return Err(format!("Unexpected argument {}", positional[expected]));
+            }
+            command
+        }
+        Some(command) => {
+            if rules.is_some() || redirection.is_some() {
+                return Err(format!("{} does not take --rules nor --redirection", command));
+            }
+            let method = match command {
+                "get" | "post" | "put" | "delete" => command.to_uppercase(),
+                _ => return Err(format!("Unknown command {}", command)),
+            };
+            let path = match positional.get(1) {
+                Some(path) => with_slash(path),
+                None => return Err(format!("Missing path for {}", command)),
+            };
+            if positional.len() > 2 {
+                return Err(format!("Unexpected argument {}", positional[2]));
+            }
+            if data.is_some() && (method == "GET" || method == "DELETE") {
+                return Err(format!("{} does not take --data", command));
+            }
+            Command::Call {
+                method: method,
+                path: path,
+                data: data,
+            }
+        }
+    };
+
+    Ok(Options {
+        config: config,
+        profile: profile,
+        dry_run: dry_run,
+        command: command,
+    })
+}
+
+/// `path` with a leading slash, e.g. `/me` for `me`.
+fn with_slash(path: &str) -> String {
+    if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{}", path)
+    }
+}
+
+/// Body given to `--data`: inline, `@file` or `@-` for stdin.
+fn read_data(data: &str) -> io::Result<String> {
+    let mut body = String::new();
+    if data == "@-" {
+        io::stdin().read_to_string(&mut body)?;
+    } else if data.starts_with('@') {
+        File::open(&data[1..])?.read_to_string(&mut body)?;
+    } else {
+        body.push_str(data);
+    }
+    Ok(body)
+}
+
+fn load_credential(options: &Options) -> Result<Credential, String> {
+    let loaded = match options.profile {
+        Some(ref profile) => Credential::load_profile(&options.config, profile),
+        None => Credential::load_from_file(&options.config),
+    };
+    loaded.map_err(|err| format!("Cannot load credentials from {}: {}", options.config, err))
+}
+
+fn exit_code(status: u16) -> i32 {
+    if status < 400 {
+        0
+    } else if status < 500 {
+        4
+    } else {
+        5
+    }
+}
+
+/// Body of a response, pretty-printed when it is JSON.
+fn format_body(response: &Response) -> String {
+    match serde_json::from_str::<serde_json::Value>(&response.body) {
+        Ok(value) => {
+            serde_json::to_string_pretty(&value).unwrap_or_else(|_| response.body.clone())
+        }
+        Err(_) => response.body.clone(),
+    }
+}
+
+fn call(options: &Options, method: &str, path: &str, data: &Option<String>) -> Result<i32, String> {
+    let body = match *data {
+        Some(ref data) => {
+            read_data(data).map_err(|err| format!("Cannot read data {}: {}", data, err))?
+        }
+        None => String::new(),
+    };
+    let mut client = OVHClient::with_credential(load_credential(options)?);
+    client.set_dry_run(options.dry_run);
+
+    let response = client.execute(method, path, &body).map_err(|err| err.to_string())?;
+    for call in client.planned_calls() {
+        eprintln!("Dry run, not sent: {} {} {}", call.method, call.url, call.body);
+    }
+    let output = format_body(&response);
+    if exit_code(response.status) == 0 {
+        println!("{}", output);
+    } else {
+        eprintln!("HTTP {}", response.status);
+        eprintln!("{}", output);
+    }
+    Ok(exit_code(response.status))
+}
+
+/// Access rules given to `--rules`: a preset or `@file`.
+fn read_rules(rules: &str) -> Result<Vec<AccessRule>, String> {
+    match rules {
+        "read-only" => Ok(AccessRule::read_only()),
+        "full" => Ok(AccessRule::full()),
+        _ if rules.starts_with('@') => {
+            AccessRule::from_file(&rules[1..])
+                .map_err(|err| format!("Cannot read rules {}: {}", rules, err))
+        }
+        _ => Err(format!("Unknown access rules {}, expected read-only, full or @FILE", rules)),
+    }
+}
+
+fn login(options: &Options, rules: &str, redirection: &Option<String>) -> Result<i32, String> {
+    let rules = read_rules(rules)?;
+    let client = OVHClient::with_credential(load_credential(options)?);
+    let redirection = redirection.as_ref().map(|redirection| redirection.as_str());
+
+    let pending = client.request_consumer_key(&rules, redirection).map_err(|err| err.to_string())?;
+    println!("Open this url to validate the new consumer key:\n\n    {}\n",
+             pending.validation_url);
+    println!("Waiting for validation...");
+    let wait = WaitOptions {
+        initial_delay: Duration::from_secs(2),
+        max_delay: Duration::from_secs(5),
+        timeout: Duration::from_secs(15 * 60),
+    };
+    let credential = client.wait_for_validation(&pending, &wait).map_err(|err| err.to_string())?;
+    credential.save_to_file(&options.config)
+        .map_err(|err| format!("Cannot save consumer key to {}: {}", options.config, err))?;
+    println!("Consumer key saved to the [{}] section of {}",
+             credential.endpoint(),
+             options.config);
+    Ok(0)
+}
+
+/// Client for calls which need no consumer key, working without config
+/// file for the default or a known endpoint.
+fn public_client(options: &Options) -> Result<OVHClient, String> {
+    let credential = match load_credential(options) {
+        Ok(credential) => credential,
+        Err(err) => {
+            let endpoint = match options.profile {
+                None => "ovh-eu",
+                Some(ref profile) if config::is_endpoint(profile) => profile,
+                Some(_) => return Err(err),
+            };
+            Credential::new_with_application(endpoint, "", "")
+        }
+    };
+    Ok(OVHClient::with_credential(credential))
+}
+
+fn api_list(options: &Options) -> Result<i32, String> {
+    let families = public_client(options)?.list_apis().map_err(|err| err.to_string())?;
+    for family in families {
+        println!("{:<40} {}",
+                 family.path,
+                 family.description.unwrap_or_else(String::new));
+    }
+    Ok(0)
+}
+
+fn api_search(options: &Options, keywords: &str, apis: &[String]) -> Result<i32, String> {
+    let client = public_client(options)?;
+    let apis = if apis.is_empty() {
+        client.list_apis()
+            .map_err(|err| err.to_string())?
+            .into_iter()
+            .map(|family| family.path)
+            .collect()
+    } else {
+        apis.to_vec()
+    };
+
+    let mut found = 0;
+    for api in &apis {
+        let schema = match client.fetch_schema(api) {
+            Ok(schema) => schema,
+            Err(err) => {
+                eprintln!("Cannot download schema of {}: {}", api, err);
+                continue;
+            }
+        };
+        for endpoint in schema.search(keywords) {
+            println!("{}", endpoint.summary());
+            found += 1;
+        }
+    }
+    if found == 0 {
+        eprintln!("No operation matches {:?}", keywords);
+        return Ok(1);
+    }
+    Ok(0)
+}
+
+fn api_show(options: &Options, method: &str, path: &str) -> Result<i32, String> {
+    let client = public_client(options)?;
+    let families = client.list_apis().map_err(|err| err.to_string())?;
+    let family = explorer::family_of(&families, path)
+        .ok_or_else(|| format!("No API serves {}", path))?;
+    let schema = client.fetch_schema(&family.path).map_err(|err| err.to_string())?;
+    let found = schema.operation(method, path)
+        .ok_or_else(|| format!("No operation {} {} in {}", method, path, family.path))?;
+    println!("{}", Endpoint::from(found).describe());
+    Ok(0)
+}
+
+fn run(options: &Options) -> Result<i32, String> {
+    match options.command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        Command::Call { ref method, ref path, ref data } => call(options, method, path, data),
+        Command::Login { ref rules, ref redirection } => login(options, rules, redirection),
+        Command::ApiList => api_list(options),
+        Command::ApiSearch { ref keywords, ref apis } => api_search(options, keywords, apis),
+        Command::ApiShow { ref method, ref path } => api_show(options, method, path),
+    }
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1),
+                                   env::var("OVH_CONFIG").ok(),
+                                   env::var("OVH_PROFILE").ok()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    let code = match run(&options) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    };
+    process::exit(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exit_code, parse_args, public_client, Command};
+
+    fn parse(args: &[&str]) -> Result<super::Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()), None, None)
+    }
+
+    #[test]
+    fn test_parse_call() {
+        let options = parse(&["-p", "ovh-ca", "post", "order/cart", "--data", "@cart.json"])
+            .unwrap();
+        assert_eq!(Some("ovh-ca".to_string()), options.profile);
+        assert_eq!(Command::Call {
+                       method: "POST".to_string(),
+                       path: "/order/cart".to_string(),
+                       data: Some("@cart.json".to_string()),
+                   },
+                   options.command);
+        assert_eq!(Command::Help, parse(&[]).unwrap().command);
+    }
+
+    #[test]
+    fn test_parse_env_defaults() {
+        let options = parse(&["get", "/me"]).unwrap();
+        assert_eq!("Config.toml", options.config);
+        assert_eq!(None, options.profile);
+
+        let args = ["-p", "ovh-ca", "api", "list"];
+        let options = parse_args(args.iter().map(|arg| arg.to_string()),
+                                 Some("/etc/ovh.toml".to_string()),
+                                 Some("ovh-us".to_string()))
+            .unwrap();
+        assert_eq!("/etc/ovh.toml", options.config);
+        assert_eq!(Some("ovh-ca".to_string()), options.profile);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(true, parse(&["patch", "/me"]).is_err());
+        assert_eq!(true, parse(&["get"]).is_err());
+        assert_eq!(true, parse(&["get", "/me", "--data", "{}"]).is_err());
+        assert_eq!(true, parse(&["get", "/me", "--verbose"]).is_err());
+        assert_eq!(true, parse(&["get", "/me", "--profile"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_login() {
+        assert_eq!(Command::Login {
+                       rules: "read-only".to_string(),
+                       redirection: None,
+                   },
+                   parse(&["auth", "login"]).unwrap().command);
+        assert_eq!(Command::Login {
+                       rules: "@rules.json".to_string(),
+                       redirection: Some("https://example.com".to_string()),
+                   },
+                   parse(&["auth", "login", "-r", "@rules.json", "--redirection",
+                           "https://example.com"])
+                       .unwrap()
+                       .command);
+        assert_eq!(true, parse(&["auth", "logout"]).is_err());
+        assert_eq!(true, parse(&["get", "/me", "--rules", "full"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_api() {
+        assert_eq!(Command::ApiList, parse(&["api", "list"]).unwrap().command);
+        assert_eq!(Command::ApiSearch {
+                       keywords: "dns record".to_string(),
+                       apis: vec!["domain".to_string()],
+                   },
+                   parse(&["api", "search", "dns record", "domain"]).unwrap().command);
+        assert_eq!(Command::ApiShow {
+                       method: "POST".to_string(),
+                       path: "/domain/zone/{zoneName}/record".to_string(),
+                   },
+                   parse(&["api", "show", "post", "domain/zone/{zoneName}/record"])
+                       .unwrap()
+                       .command);
+        assert_eq!(true, parse(&["api", "list", "domain"]).is_err());
+        assert_eq!(true, parse(&["api", "show", "get"]).is_err());
+        assert_eq!(true, parse(&["api", "search"]).is_err());
+    }
+
+    #[test]
+    fn test_public_client() {
+        let options = parse(&["-c", "missing.toml", "api", "list"]).unwrap();
+        assert_eq!("eu.api.ovh.com", public_client(&options).unwrap().credential.host);
+        let options = parse(&["-c", "missing.toml", "-p", "ovh-ca", "api", "list"]).unwrap();
+        assert_eq!("ca.api.ovh.com", public_client(&options).unwrap().credential.host);
+        let options = parse(&["-c", "missing.toml", "-p", "work", "api", "list"]).unwrap();
+        assert_eq!(true, public_client(&options).is_err());
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(0, exit_code(200));
+        assert_eq!(4, exit_code(404));
+        assert_eq!(5, exit_code(503));
+    }
+}
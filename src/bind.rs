@@ -0,0 +1,425 @@
+//! # BIND
+//!
+//! Zone files in BIND format, as exported by `/domain/zone/{zoneName}/export`
+//! and imported by `/domain/zone/{zoneName}/import`, e.g. for backups and
+//! migrations.
+//!
+//! `parse` reads a zone file into typed records locally, `import_zone`
+//! checks the file with it before uploading it.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//! use ovh::bind;
+//!
+//! let client = OVHClient::new();
+//! let zone_file = client.export_zone("example.com").unwrap();
+//! for record in bind::parse("example.com", &zone_file).unwrap() {
+//!     println!("{} {} {}", record.sub_domain, record.field_type, record.target);
+//! }
+//! client.import_zone("example.org", &zone_file.replace("example.com.", "example.org.")).unwrap();
+//! ```
+//!
+extern crate serde_json;
+
+use std::error;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use self::serde_json::Value;
+
+use client::OVHClient;
+use codegen::{decode, encode};
+use dns::{FieldType, NewRecord};
+use error::Error;
+
+/// Why a zone file is invalid, and on which line.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for ParseError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// A directive or record, once comments are removed and parentheses joined.
+struct Entry {
+    line: usize,
+    /// The owner is the previous one when the line starts with a blank.
+    blank_owner: bool,
+    tokens: Vec<String>,
+}
+
+/// Split a zone file into entries, keeping quoted strings as one token.
+fn entries(text: &str) -> Result<Vec<Entry>, ParseError> {
+    let mut entries = Vec::new();
+    let mut current: Option<Entry> = None;
+    let mut depth = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let mut entry = match current.take() {
+            Some(entry) => entry,
+            None => {
+                Entry {
+                    line: number,
+                    blank_owner: line.starts_with(' ') || line.starts_with('\t'),
+                    tokens: Vec::new(),
+                }
+            }
+        };
+        let mut token = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            if quoted {
+                token.push(c);
+                if c == '\\' {
+                    token.extend(chars.next());
+                } else if c == '"' {
+                    quoted = false;
+                }
+                continue;
+            }
+            match c {
+                ';' => break,
+                '"' => {
+                    quoted = true;
+                    token.push(c);
+                }
+                '(' | ')' | ' ' | '\t' => {
+                    if !token.is_empty() {
+                        entry.tokens.push(token.clone());
+                        token.clear();
+                    }
+                    if c == '(' {
+                        depth += 1;
+                    } else if c == ')' {
+                        if depth == 0 {
+                            return Err(ParseError {
+                                line: number,
+                                message: "unbalanced )".to_string(),
+                            });
+                        }
+                        depth -= 1;
+                    }
+                }
+                _ => token.push(c),
+            }
+        }
+        if quoted {
+            return Err(ParseError {
+                line: number,
+                message: "unterminated string".to_string(),
+            });
+        }
+        if !token.is_empty() {
+            entry.tokens.push(token);
+        }
+
+        if depth > 0 {
+            current = Some(entry);
+        } else if !entry.tokens.is_empty() {
+            entries.push(entry);
+        }
+    }
+    if let Some(entry) = current {
+        return Err(ParseError {
+            line: entry.line,
+            message: "unbalanced (".to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Parse a TTL, in seconds or with units, e.g. `3600` or `1h`.
+fn parse_ttl(ttl: &str) -> Option<u32> {
+    if let Ok(seconds) = ttl.parse() {
+        return Some(seconds);
+    }
+    let mut total: u32 = 0;
+    let mut number = String::new();
+    for c in ttl.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+        let value: u32 = number.parse().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        number.clear();
+    }
+    if number.is_empty() { Some(total) } else { None }
+}
+
+/// Fully qualified form of `name`, relative to `origin` unless it ends with a dot.
+fn absolute(name: &str, origin: &str) -> String {
+    if name == "@" {
+        origin.to_string()
+    } else if name.ends_with('.') {
+        name.to_lowercase()
+    } else {
+        format!("{}.{}", name.to_lowercase(), origin)
+    }
+}
+
+/// Check the data of a record of type `field_type`.
+fn check_rdata(field_type: &FieldType, rdata: &[String]) -> Result<(), String> {
+    let count = |expected: usize| if rdata.len() == expected {
+        Ok(())
+    } else {
+        Err(format!("{} takes {} values, got {}", field_type, expected, rdata.len()))
+    };
+    let number = |value: &str| {
+        value.parse::<u16>()
+            .map(|_| ())
+            .map_err(|_| format!("{} expects a number, got {}", field_type, value))
+    };
+    match *field_type {
+        FieldType::A => {
+            count(1)?;
+            rdata[0].parse::<Ipv4Addr>()
+                .map(|_| ())
+                .map_err(|_| format!("invalid IPv4 address {}", rdata[0]))
+        }
+        FieldType::AAAA => {
+            count(1)?;
+            rdata[0].parse::<Ipv6Addr>()
+                .map(|_| ())
+                .map_err(|_| format!("invalid IPv6 address {}", rdata[0]))
+        }
+        FieldType::CNAME | FieldType::DNAME | FieldType::NS | FieldType::PTR => count(1),
+        FieldType::MX => {
+            count(2)?;
+            number(&rdata[0])
+        }
+        FieldType::SRV => {
+            count(4)?;
+            rdata[..3].iter().try_for_each(|value| number(value))
+        }
+        FieldType::CAA => {
+            count(3)?;
+            number(&rdata[0])
+        }
+        _ if rdata.is_empty() => Err(format!("{} takes at least one value", field_type)),
+        _ => Ok(()),
+    }
+}
+
+/// Parse the zone file of `zone`, e.g. `example.com`, into records.
+///
+/// `$ORIGIN` and `$TTL` directives, `@`, relative and absolute names,
+/// multi-line entries and comments are supported. The SOA record is
+/// checked but not returned since OVH manages it, nor is the `$TTL`
+/// default: records without TTL get the zone's default one.
+pub fn parse(zone: &str, text: &str) -> Result<Vec<NewRecord>, ParseError> {
+    let apex = format!("{}.", zone.trim_end_matches('.').to_lowercase());
+    let mut origin = apex.clone();
+    let mut owner: Option<String> = None;
+    let mut records = Vec::new();
+
+    for entry in entries(text)? {
+        let line = entry.line;
+        let error = |message: String| {
+            ParseError {
+                line: line,
+                message: message,
+            }
+        };
+        let mut tokens = entry.tokens.iter().map(|token| token.as_str());
+
+        if !entry.blank_owner && entry.tokens[0].starts_with('$') {
+            let directive = tokens.next().unwrap_or("");
+            let value = tokens.next()
+                .ok_or_else(|| error(format!("missing value for {}", directive)))?;
+            match directive {
+                "$ORIGIN" => origin = absolute(value, &origin),
+                "$TTL" => {
+                    parse_ttl(value).ok_or_else(|| error(format!("invalid TTL {}", value)))?;
+                }
+                _ => return Err(error(format!("unsupported directive {}", directive))),
+            }
+            continue;
+        }
+
+        if !entry.blank_owner {
+            owner = tokens.next().map(|name| absolute(name, &origin));
+        }
+        let name = owner.clone().ok_or_else(|| error("missing owner name".to_string()))?;
+
+        let mut ttl = None;
+        let mut field_type = None;
+        for token in &mut tokens {
+            match token.to_uppercase().as_str() {
+                "IN" | "CH" | "HS" => {}
+                _ if ttl.is_none() && token.starts_with(|c: char| c.is_ascii_digit()) => {
+                    ttl = Some(parse_ttl(token)
+                        .ok_or_else(|| error(format!("invalid TTL {}", token)))?);
+                }
+                upper => {
+                    field_type = Some(upper.to_string());
+                    break;
+                }
+            }
+        }
+        let field_type = field_type.ok_or_else(|| error("missing record type".to_string()))?;
+        let rdata: Vec<String> = tokens.map(|token| token.to_string()).collect();
+
+        if field_type == "SOA" {
+            if rdata.len() != 7 {
+                return Err(error(format!("SOA takes 7 values, got {}", rdata.len())));
+            }
+            continue;
+        }
+        let field_type = match FieldType::from_api(&field_type) {
+            FieldType::Other(other) => return Err(error(format!("unknown record type {}", other))),
+            field_type => field_type,
+        };
+        check_rdata(&field_type, &rdata).map_err(&error)?;
+
+        let sub_domain = if name == apex {
+            String::new()
+        } else if name.ends_with(&format!(".{}", apex)) {
+            name[..name.len() - apex.len() - 1].to_string()
+        } else {
+            return Err(error(format!("{} is not in zone {}", name, apex)));
+        };
+        records.push(NewRecord {
+            field_type: field_type,
+            sub_domain: sub_domain,
+            target: rdata.join(" "),
+            ttl: ttl,
+        });
+    }
+    Ok(records)
+}
+
+impl OVHClient {
+    /// Zone file of `zone` in BIND format.
+    pub fn export_zone(&self, zone: &str) -> Result<String, Error> {
+        decode(self.execute("GET", &format!("/domain/zone/{}/export", encode(zone)), "")?)
+    }
+
+    /// Replace all the records of `zone` with those of `zone_file`,
+    /// returning the import task, see `wait_for_task`.
+    ///
+    /// The file is parsed first, an invalid one returns `Error::ZoneFile`
+    /// and is not uploaded.
+    pub fn import_zone(&self, zone: &str, zone_file: &str) -> Result<Value, Error> {
+        parse(zone, zone_file).map_err(Error::ZoneFile)?;
+        let mut body = serde_json::Map::new();
+        body.insert("zoneFile".to_string(), Value::String(zone_file.to_string()));
+        let path = format!("/domain/zone/{}/import", encode(zone));
+        decode(self.execute("POST", &path, &Value::Object(body).to_string())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, parse_ttl, ParseError};
+    use dns::{FieldType, NewRecord};
+    use dns::tests::client;
+    use error::Error;
+
+    /// Zone file as exported by OVH.
+    const EXPORT: &'static str = "$TTL 3600
+@\tIN SOA dns200.anycast.me. tech.ovh.net. (2024010101 86400 3600 3600000 60)
+                 IN NS     dns200.anycast.me.
+                 IN NS     ns200.anycast.me.
+                 IN MX     1 mx1.mail.ovh.net.
+                 IN A      192.0.2.1
+                 IN TXT    \"v=spf1 include:mx.ovh.com ~all\"
+_dmarc       60  IN TXT    \"v=DMARC1; p=none\" ; policy
+www              IN CNAME  example.com.
+$ORIGIN sub.example.com.
+api          1h  IN AAAA   2001:db8::1
+";
+
+    fn error(zone_file: &str) -> ParseError {
+        parse("example.com", zone_file).unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let records = parse("example.com", EXPORT).unwrap();
+        assert_eq!(8, records.len());
+        assert_eq!(NewRecord::new(FieldType::NS, "", "dns200.anycast.me."), records[0]);
+        assert_eq!("1 mx1.mail.ovh.net.", records[2].target);
+        assert_eq!(NewRecord {
+                       field_type: FieldType::TXT,
+                       sub_domain: "_dmarc".to_string(),
+                       target: "\"v=DMARC1; p=none\"".to_string(),
+                       ttl: Some(60),
+                   },
+                   records[5]);
+        assert_eq!("www", records[6].sub_domain);
+        assert_eq!("api.sub", records[7].sub_domain);
+        assert_eq!(Some(3600), records[7].ttl);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(ParseError {
+                       line: 2,
+                       message: "invalid IPv4 address 192.0.2".to_string(),
+                   },
+                   error("www IN A 192.0.2.1\nftp IN A 192.0.2\n"));
+        assert_eq!(1, error("@ IN SOA a. b. (1 2 3 4 5\n").line);
+        assert_eq!("unterminated string", error("@ IN TXT \"v=spf1\n").message);
+        assert_eq!("unknown record type WKS", error("@ IN WKS 1").message);
+        assert_eq!("MX takes 2 values, got 1", error("@ IN MX mx1.mail.ovh.net.").message);
+        assert_eq!("www.example.org. is not in zone example.com.",
+                   error("www.example.org. IN A 192.0.2.1").message);
+        assert_eq!("missing owner name", error("  IN A 192.0.2.1").message);
+    }
+
+    #[test]
+    fn test_parse_ttl() {
+        assert_eq!(Some(300), parse_ttl("300"));
+        assert_eq!(Some(5400), parse_ttl("1h30m"));
+        assert_eq!(None, parse_ttl("1y"));
+        assert_eq!(None, parse_ttl("30m5"));
+    }
+
+    #[test]
+    fn test_import_invalid() {
+        let (client, api) = client(vec![("POST", "/domain/zone/example.com/import", "{}")]);
+        match client.import_zone("example.com", "www IN A 192.0.2\n") {
+            Err(Error::ZoneFile(err)) => assert_eq!(1, err.line),
+            other => panic!("expected an invalid zone file, got {:?}", other),
+        }
+        assert_eq!(0, api.sent.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_export_import() {
+        let (client, api) = client(vec![
+            ("GET", "/domain/zone/example.com/export", r#""$TTL 3600\nwww IN A 192.0.2.1\n""#),
+            ("POST", "/domain/zone/example.com/import", r#"{"id": 42, "status": "todo"}"#),
+        ]);
+        let zone_file = client.export_zone("example.com").unwrap();
+        assert_eq!("$TTL 3600\nwww IN A 192.0.2.1\n", zone_file);
+        assert_eq!(1, parse("example.com", &zone_file).unwrap().len());
+
+        let task = client.import_zone("example.com", &zone_file).unwrap();
+        assert_eq!(Some(42), task.get("id").and_then(|id| id.as_u64()));
+        let sent = api.sent.lock().unwrap();
+        assert_eq!(r#"{"zoneFile":"$TTL 3600\nwww IN A 192.0.2.1\n"}"#, sent[1].body);
+    }
+}
@@ -0,0 +1,272 @@
+//! # Cassette
+//!
+//! Record real API interactions to a file, then replay them
+//! deterministically, e.g. to test complex flows offline.
+//!
+//! Application keys, consumer keys and signatures are scrubbed from
+//! recorded requests, and `consumerKey` and `password` fields from bodies.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//! use ovh::cassette::{RecordingTransport, ReplayTransport};
+//!
+//! // Record once against the real API...
+//! let mut client = OVHClient::new();
+//! let recorder = RecordingTransport::new(client.transport(), "tests/cart.json");
+//! client.set_transport(recorder);
+//! client.execute("POST", "/order/cart", r#"{"ovhSubsidiary": "FR"}"#).unwrap();
+//!
+//! // ...then replay in tests.
+//! let mut client = OVHClient::new();
+//! client.set_transport(ReplayTransport::load("tests/cart.json").unwrap());
+//! client.execute("POST", "/order/cart", r#"{"ovhSubsidiary": "FR"}"#).unwrap();
+//! ```
+//!
+extern crate serde_json;
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use self::serde_json::Value;
+
+use client::{Request, Response};
+use error::Error;
+use transport::Transport;
+
+const SCRUBBED: &'static str = "***";
+const SCRUBBED_HEADERS: [&'static str; 3] = ["X-Ovh-Application",
+                                             "X-Ovh-Consumer",
+                                             "X-Ovh-Signature"];
+const SCRUBBED_FIELDS: [&'static str; 2] = ["consumerKey", "password"];
+
+#[derive(Debug,Clone,Serialize,Deserialize)]
+struct Interaction {
+    request: Request,
+    response: Response,
+}
+
+#[derive(Debug,Clone,Serialize,Deserialize)]
+struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+/// Remove credentials from a request before it is written to a cassette.
+fn scrub_request(request: &Request) -> Request {
+    let headers = request.headers
+        .iter()
+        .map(|&(ref name, ref value)| {
+            let secret = SCRUBBED_HEADERS.iter().any(|h| name.eq_ignore_ascii_case(h));
+            let value = if secret { SCRUBBED.to_string() } else { value.clone() };
+            (name.clone(), value)
+        })
+        .collect();
+    Request {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        headers: headers,
+        body: scrub_body(&request.body),
+    }
+}
+
+fn scrub_response(response: &Response) -> Response {
+    Response {
+        status: response.status,
+        headers: response.headers.clone(),
+        body: scrub_body(&response.body),
+    }
+}
+
+/// Replace the `consumerKey` and `password` fields of a JSON body, at any depth.
+pub(crate) fn scrub_body(body: &str) -> String {
+    fn scrub(value: &mut Value) -> bool {
+        match *value {
+            Value::Object(ref mut map) => {
+                let mut changed = false;
+                for (key, value) in map.iter_mut() {
+                    if SCRUBBED_FIELDS.contains(&key.as_str()) {
+                        *value = Value::String(SCRUBBED.to_string());
+                        changed = true;
+                    } else {
+                        changed |= scrub(value);
+                    }
+                }
+                changed
+            }
+            Value::Array(ref mut values) => {
+                values.iter_mut().fold(false, |changed, value| scrub(value) || changed)
+            }
+            _ => false,
+        }
+    }
+
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut value) => {
+            if scrub(&mut value) {
+                serde_json::to_string(&value).unwrap_or_else(|_| body.to_string())
+            } else {
+                body.to_string()
+            }
+        }
+        Err(_) => body.to_string(),
+    }
+}
+
+/// Path and query string of a url, so that cassettes do not depend on the endpoint.
+fn path_and_query(url: &str) -> &str {
+    match url.find("://") {
+        Some(scheme) => {
+            let rest = &url[scheme + 3..];
+            rest.find('/').map(|pos| &rest[pos..]).unwrap_or("/")
+        }
+        None => url,
+    }
+}
+
+fn matches(recorded: &Request, request: &Request) -> bool {
+    recorded.method == request.method &&
+    path_and_query(&recorded.url) == path_and_query(&request.url) &&
+    recorded.body == request.body
+}
+
+/// Send requests through another transport and record them to a file,
+/// rewritten after each interaction.
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    path: PathBuf,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl RecordingTransport {
+    pub fn new<P: AsRef<Path>>(inner: Arc<dyn Transport>, path: P) -> RecordingTransport {
+        RecordingTransport {
+            inner: inner,
+            path: path.as_ref().to_path_buf(),
+            interactions: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn save(&self, interactions: &[Interaction]) -> io::Result<()> {
+        let cassette = Cassette { interactions: interactions.to_vec() };
+        let content = serde_json::to_string_pretty(&cassette)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        File::create(&self.path)?.write_all(content.as_bytes())
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send(&self, request: &Request) -> Result<Response, Error> {
+        let response = self.inner.send(request)?;
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push(Interaction {
+            request: scrub_request(request),
+            response: scrub_response(&response),
+        });
+        if let Err(err) = self.save(&interactions) {
+            warn!("Cannot write cassette {}: {}", self.path.display(), err);
+        }
+        Ok(response)
+    }
+}
+
+/// Serve the interactions of a cassette.
+///
+/// A request is answered with the first interaction not replayed yet with
+/// the same method, path, query string and body. Headers are not compared.
+pub struct ReplayTransport {
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl ReplayTransport {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<ReplayTransport> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        let cassette: Cassette = serde_json::from_str(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(ReplayTransport {
+            interactions: Mutex::new(cassette.interactions
+                .into_iter()
+                .map(|interaction| (interaction, false))
+                .collect()),
+        })
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: &Request) -> Result<Response, Error> {
+        let request = scrub_request(request);
+        let mut interactions = self.interactions.lock().unwrap();
+        let found = interactions.iter_mut()
+            .find(|entry| !entry.1 && matches(&entry.0.request, &request));
+        match found {
+            Some(entry) => {
+                entry.1 = true;
+                Ok(entry.0.response.clone())
+            }
+            None => {
+                Err(Error::Http(format!("No recorded interaction for {} {}",
+                                        request.method,
+                                        path_and_query(&request.url))))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::{path_and_query, scrub_body, RecordingTransport, ReplayTransport};
+    use client::Request;
+    use transport::Transport;
+    use transport::tests::Stub;
+
+    fn request(url: &str) -> Request {
+        Request {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: vec![("X-Ovh-Consumer".to_string(), "ck".to_string()),
+                          ("X-Ovh-Signature".to_string(), "$1$abc".to_string())],
+            body: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_path_and_query() {
+        assert_eq!("/1.0/me?x=1", path_and_query("https://eu.api.ovh.com/1.0/me?x=1"));
+        assert_eq!("/1.0/me", path_and_query("/1.0/me"));
+    }
+
+    #[test]
+    fn test_scrub_body() {
+        assert_eq!(r#"{"consumerKey":"***","state":"pendingValidation"}"#,
+                   scrub_body(r#"{"consumerKey":"ck","state":"pendingValidation"}"#));
+        assert_eq!(r#"[{"login":"example.com-office","password":"***"}]"#,
+                   scrub_body(r#"[{"login":"example.com-office","password":"hunter22"}]"#));
+        assert_eq!("not json", scrub_body("not json"));
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let path = env::temp_dir().join("ovh-rs-test-cassette.json");
+        let api = Stub::new();
+        api.on("GET", "/me", 200, r#"{"consumerKey":"ck","url":"/1.0/me"}"#);
+        api.on("GET", "/me/bill", 200, r#"{"consumerKey":"ck","url":"/1.0/me/bill"}"#);
+        let recorder = RecordingTransport::new(api, &path);
+        recorder.send(&request("https://eu.api.ovh.com/1.0/me")).unwrap();
+        recorder.send(&request("https://eu.api.ovh.com/1.0/me/bill")).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(false, content.contains("$1$abc"));
+        assert_eq!(false, content.contains("\"ck\""));
+
+        let replay = ReplayTransport::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let response = replay.send(&request("https://ca.api.ovh.com/1.0/me/bill")).unwrap();
+        assert_eq!(r#"{"consumerKey":"***","url":"/1.0/me/bill"}"#, response.body);
+        assert_eq!(true, replay.send(&request("https://eu.api.ovh.com/1.0/me")).is_ok());
+        assert_eq!(true, replay.send(&request("https://eu.api.ovh.com/1.0/me")).is_err());
+    }
+}
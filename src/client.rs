@@ -7,46 +7,262 @@
 extern crate serde;
 extern crate serde_json;
 
+use clock::{Clock, SystemClock};
 use config::Credential;
-use std::io::Read;
+use dry_run::{self, PlannedCall};
+use error::Error;
+use middleware::{Middleware, Next};
+use reload::Reloader;
+use secret::Secret;
+use transport::{self, HttpTransport, ResponseSink, Transport};
+use validate::Validator;
+use std::cell::RefCell;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+#[cfg(feature = "tracing")]
+use trace;
+
+use signature::{self, Payload};
 
 #[cfg(not(feature = "curl"))]
-use reqwest;
-#[cfg(not(feature = "curl"))]
-use reqwest::Response;
-#[cfg(not(feature = "curl"))]
-use hyper::header::{Headers, UserAgent, Accept, qitem, ContentType};
-#[cfg(not(feature = "curl"))]
-use hyper::mime::{Value, Mime, TopLevel, SubLevel, Attr};
-
-#[cfg(feature = "curl")]
-use curl;
+const USER_AGENT: &'static str = "OVH-rs/hyper/0.10";
 #[cfg(feature = "curl")]
-use curl::easy::{Easy, List};
+const USER_AGENT: &'static str = "OVH-rs/curl-rust/0.4";
+
+/// A signed request, as seen by middlewares right before it is sent.
+///
+/// The consumer key header is redacted from `Debug` output.
+#[derive(Clone,Serialize,Deserialize)]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
 
-use chrono::*;
+impl Request {
+    /// Value of the header `name`, case insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
 
-use crypto::digest::Digest;
-use crypto::sha1::Sha1;
+    /// Set the header `name`, replacing any previous value.
+    ///
+    /// Headers are not part of the signature, unlike the method, url and body.
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.headers.retain(|&(ref n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+}
 
-// Required headers for auth
-header! { (XOvhApplication, "X-Ovh-Application") => [String] }
-header! { (XOvhTimestamp, "X-Ovh-Timestamp") => [String] }
-header! { (XOvhSignature, "X-Ovh-Signature") => [String] }
-header! { (XOvhConsumer, "X-Ovh-Consumer") => [String] }
+impl fmt::Debug for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let headers: Vec<(&str, &str)> = self.headers
+            .iter()
+            .map(|&(ref name, ref value)| if name.eq_ignore_ascii_case("X-Ovh-Consumer") {
+                (name.as_str(), "***")
+            } else {
+                (name.as_str(), value.as_str())
+            })
+            .collect();
+        f.debug_struct("Request")
+            .field("method", &self.method)
+            .field("url", &self.url)
+            .field("headers", &headers)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+/// Response to a `Request`.
+#[derive(Debug,Clone,Serialize,Deserialize)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    /// Value of the header `name`, case insensitive.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Turn a response with a 4xx or 5xx status into an `Error::Api`,
+    /// with the `message` OVH gives in the body.
+    pub fn error_for_status(self) -> Result<Response, Error> {
+        if self.status < 400 {
+            return Ok(self);
+        }
+        let message = serde_json::from_str::<serde_json::Value>(&self.body)
+            .ok()
+            .and_then(|body| body.get("message").and_then(|m| m.as_str()).map(String::from))
+            .unwrap_or(self.body);
+        Err(Error::Api {
+            status: self.status,
+            message: message,
+        })
+    }
+}
 
-#[derive(Debug,Clone)]
+/// Replace the consumer key of `credential`, if any is given.
+fn with_consumer_key(mut credential: Credential, consumer_key: Option<&str>) -> Credential {
+    if let Some(consumer_key) = consumer_key {
+        credential.consumer_key = Secret::new(consumer_key);
+    }
+    credential
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter()
+        .find(|&&(ref n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, ref v)| v.as_str())
+}
+
+#[derive(Clone)]
 pub struct OVHClient {
     pub credential: Credential,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    reloader: Option<Arc<Reloader>>,
+    base_url: Option<String>,
+    // Shared by clones, so that connections are reused
+    transport: Arc<dyn Transport>,
+    // Calls skipped in dry-run mode, shared by clones
+    plan: Option<Arc<Mutex<Vec<PlannedCall>>>>,
+    clock: Arc<dyn Clock>,
+    validator: Option<Arc<Validator>>,
+}
+
+impl fmt::Debug for OVHClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OVHClient")
+            .field("credential", &self.credential)
+            .field("middlewares", &self.middlewares.len())
+            .field("reloading", &self.reloader.is_some())
+            .field("base_url", &self.base_url)
+            .field("dry_run", &self.plan.is_some())
+            .field("validating", &self.validator.is_some())
+            .finish()
+    }
 }
 
 impl OVHClient {
 
     /// Initialize a new `Credential` from default path a App Key, App secret, Consumer token.
     pub fn new() -> OVHClient {
-        #[cfg(feature = "curl")]
-        curl::init();
-        OVHClient { credential: Credential::new() }
+        OVHClient::with_credential(Credential::new())
+    }
+
+    /// Initialize a new client from an existing `Credential`.
+    pub fn with_credential(credential: Credential) -> OVHClient {
+        OVHClient {
+            credential: credential,
+            middlewares: Vec::new(),
+            reloader: None,
+            base_url: None,
+            transport: Arc::new(HttpTransport::new()),
+            plan: None,
+            clock: Arc::new(SystemClock),
+            validator: None,
+        }
+    }
+
+    /// Replace the transport requests are sent with, e.g. to record or
+    /// replay them, see `cassette`.
+    pub fn set_transport<T: Transport + 'static>(&mut self, transport: T) {
+        self.transport = Arc::new(transport);
+    }
+
+    /// Replace the clock requests are timestamped with, e.g. with a
+    /// `FixedClock` to reproduce signatures.
+    ///
+    /// The skew with OVH's clock is still measured against it.
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.clock = Arc::new(clock);
+    }
+
+    /// Transport requests are sent with, e.g. to wrap it.
+    pub fn transport(&self) -> Arc<dyn Transport> {
+        self.transport.clone()
+    }
+
+    /// Initialize a new client whose `Credential` follows the file at `path`.
+    ///
+    /// The file is re-read before a call when its modification time
+    /// changed, and when the API rejects a call with 401 or 403. Nothing
+    /// watches the file in between calls. Calls already in flight keep
+    /// the `Credential` they were signed with.
+    pub fn new_reloading<P: AsRef<Path>>(path: P) -> OVHClient {
+        let reloader = match Reloader::new(path) {
+            Err(_) => panic!("Could not read auth"),
+            Ok(reloader) => reloader,
+        };
+        let mut client = OVHClient::with_credential(reloader.current());
+        client.reloader = Some(Arc::new(reloader));
+        client
+    }
+
+    /// `Credential` the next call will be signed with.
+    ///
+    /// For a reloading client, this is the latest one read from the file
+    /// rather than the `credential` field, which keeps the initial one.
+    pub fn current_credential(&self) -> Credential {
+        match self.reloader {
+            Some(ref reloader) => reloader.current(),
+            None => self.credential.clone(),
+        }
+    }
+
+    /// Enable or disable dry-run mode.
+    ///
+    /// In dry-run mode, POST, PUT, PATCH and DELETE calls are signed and go
+    /// through the middlewares, but instead of being sent they are added to
+    /// `planned_calls` and answered with `null`. GET calls are sent as usual.
+    /// Enabling it starts a new plan.
+    pub fn set_dry_run(&mut self, enabled: bool) {
+        self.plan = if enabled {
+            Some(Arc::new(Mutex::new(Vec::new())))
+        } else {
+            None
+        };
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.plan.is_some()
+    }
+
+    /// Calls skipped so far in dry-run mode, in order.
+    pub fn planned_calls(&self) -> Vec<PlannedCall> {
+        match self.plan {
+            Some(ref plan) => plan.lock().unwrap().clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Check calls against API schemas before signing them, or stop
+    /// checking them with `None`, see `validate`.
+    ///
+    /// Calls which do not match return `Error::Validation` and are not sent.
+    pub fn set_validator(&mut self, validator: Option<Validator>) {
+        self.validator = validator.map(Arc::new);
+    }
+
+    /// Check a call against the validator's schemas, if any.
+    fn validate(&self, method: &str, query: &str, body: &str) -> Result<(), Error> {
+        match self.validator {
+            Some(ref validator) => validator.validate(method, query, body),
+            None => Ok(()),
+        }
+    }
+
+    /// Append a middleware to the chain run by `execute`.
+    ///
+    /// Middlewares are run in the order they were added.
+    pub fn add_middleware<M: Middleware + 'static>(&mut self, middleware: M) {
+        self.middlewares.push(Arc::new(middleware));
     }
 
     /// Compute signature for OVH.
@@ -57,91 +273,41 @@ impl OVHClient {
                  aas: &str,
                  ck: &str)
                  -> String {
-        let sep = "+";
-        let prefix = "$1$".to_string();
-
-        let capacity = 1 + &aas.len() + &sep.len() + &ck.len() + &method.len() + &sep.len() +
-                       &query.len() + &sep.len() +
-                       &body.len() + &sep.len() + &timestamp.len();
-        let mut signature = String::with_capacity(capacity);
-        signature.push_str(&aas);
-        signature.push_str(&sep);
-        signature.push_str(&ck);
-        signature.push_str(&sep);
-        signature.push_str(&method);
-        signature.push_str(&sep);
-        signature.push_str(&query);
-        signature.push_str(&sep);
-        signature.push_str(&body);
-        signature.push_str(&sep);
-        signature.push_str(&timestamp);
-
-        debug!("Signature: {}", &signature);
-        let mut hasher = Sha1::new();
-        hasher.input_str(&signature);
-        let hex = hasher.result_str();
-        debug!("hex: {}", &hex);
-
-        let sign = prefix + &hex;
-        sign
-    }
-
-    /// Ask time to OVH API server to compute delta time
-    #[cfg(not(feature = "curl"))]
-    fn remote_time() -> u64 {
-        let query = "https://eu.api.ovh.com/1.0/auth/time".to_string();
-        // Create a client.
-        let client = reqwest::Client::new().unwrap();
-
-        // Creating an outgoing request.
-        let mut res = client.get(&query)
-            .send()
-            .unwrap();
-
-        let mut body = String::new();
-        res.read_to_string(&mut body).unwrap();
-
-        match body.parse::<u64>() {
-            Ok(time) => time,
-            Err(_) => 1,
-        }
+        let payload = Payload {
+            method: method,
+            url: query,
+            body: body,
+            timestamp: timestamp,
+        };
+        signature::compute(&payload, aas, ck)
     }
 
     /// Ask time to OVH API server to compute delta time
-    #[cfg(feature = "curl")]
-    fn remote_time() -> u64 {
-        let query = "https://eu.api.ovh.com/1.0/auth/time".to_string();
-        // Create a client.
-        let mut client = Easy::new();
-        client.timeout(Duration::seconds(20).to_std().unwrap());
-
-        let mut response_data = Vec::new();
-        client.url(&query).unwrap();
-        client.get(true).unwrap();
-        {
-            let mut transfer = client.transfer();
-            transfer.write_function(|buf| {
-                response_data.extend_from_slice(buf);
-                Ok(buf.len())
-            }).unwrap();
-            transfer.perform().unwrap();
-        }
-        let body = String::from_utf8(response_data).unwrap();
+    fn remote_time(&self) -> u64 {
+        let request = Request {
+            method: "GET".to_string(),
+            url: self.base_url(&self.credential) + "/auth/time",
+            headers: vec![("User-Agent".to_string(), USER_AGENT.to_string())],
+            body: "".to_string(),
+        };
+        let body = match self.transport.send(&request) {
+            Ok(response) => response.body,
+            Err(err) => {
+                info!("Cannot fetch remote time: {}", err);
+                return 1;
+            }
+        };
 
-        match body.parse::<u64>() {
+        match body.trim().parse::<u64>() {
             Ok(time) => time,
             Err(_) => 1,
         }
     }
 
     /// compute delta time
-    fn compute_time_delta() -> u64 {
-        let localtime = Local::now()
-            .format("%s")
-            .to_string()
-            .parse::<u64>()
-            .unwrap();
-        let remotetime = OVHClient::remote_time();
+    fn compute_time_delta(&self) -> u64 {
+        let localtime = self.clock.now();
+        let remotetime = self.remote_time();
         if remotetime <= localtime {
             info!("fail to fetch remote time");
             0
@@ -152,210 +318,227 @@ impl OVHClient {
         }
     }
 
-    /// Start a client request with given method
-    /// Use Hyper client
-    #[cfg(not(feature = "curl"))]
-    pub fn request(credential: &Credential, method: &str, query: &str, body: &str) -> String {
-        let localtime = Local::now().format("%s").to_string().parse::<u64>().unwrap();
-        let computed_time = localtime + OVHClient::compute_time_delta();
-        let timestamp = computed_time.to_string();
-
-        let protocol = "https://".to_string();
-        let base_path = "/1.0";
-        let url = protocol + &credential.host + &base_path + &query;
-        let sign = OVHClient::build_sig(&method,
-                                        &url,
-                                        &body,
-                                        &timestamp,
-                                        credential.application_secret.as_str(),
-                                        credential.consumer_key.as_str());
-
-        // build headers
-        let mut headers = Headers::new();
-        headers.set(XOvhApplication(credential.application_key.to_string()));
-        headers.set(XOvhTimestamp(timestamp.to_string()));
-        headers.set(XOvhSignature(sign.to_string()));
-        headers.set(XOvhConsumer(credential.consumer_key.to_string()));
-        headers.set(Accept(vec![
-                qitem(Mime(
-                        TopLevel::Application,
-                        SubLevel::Json,
-                        vec![(Attr::Charset,
-                              Value::Utf8)])
-                ),
-            ]));
-        headers.set(ContentType(Mime(TopLevel::Application,
-                                     SubLevel::Json,
-                                     vec![(Attr::Charset, Value::Utf8)])));
-        headers.set(UserAgent("OVH-rs/hyper/0.10".to_owned()));
-
-        // Create a client.
-        let client = reqwest::Client::new().unwrap();
-
-        debug!("Signature: {}", sign.to_string());
+    /// Point the client to another API root than `https://{host}/1.0`,
+    /// e.g. a local mock server.
+    pub fn set_base_url(&mut self, base_url: &str) {
+        self.base_url = Some(base_url.trim_end_matches('/').to_string());
+    }
 
-        // Creating an outgoing request.
-        let res: Result<Response, reqwest::Error> = match method {
-            "HEAD" => {
-                client.head(&url)
-                    .headers(headers)
-                    .send()
+    /// API root calls are made against.
+    fn base_url(&self, credential: &Credential) -> String {
+        match self.base_url {
+            Some(ref base_url) => base_url.clone(),
+            None => {
+                let protocol = "https://".to_string();
+                let base_path = "/1.0";
+                protocol + &credential.host + &base_path
             }
-            "GET" => {
-                client.get(&url)
-                    .headers(headers)
-                    .send()
-            }
-            "POST" => {
-                client.post(&url)
-                    .headers(headers)
-                    .body(body)
-                    .send()
-            }
-            "PUT" => {
-                client.request(reqwest::Method::Put, &url)
-                    .headers(headers)
-                    .body(body)
-                    .send()
-            }
-            "PATCH" => {
-                client.request(reqwest::Method::Patch, &url)
-                    .headers(headers)
-                    .body(body)
-                    .send()
-            }
-            "DELETE" => {
-                client.request(reqwest::Method::Delete, &url)
-                    .headers(headers)
-                    .body(body)
-                    .send()
-            }
-            _ => Err(reqwest::Error::Http(reqwest::HyperError::Method)),
-        };
-        let mut body = String::new();
-        res.unwrap().read_to_string(&mut body).unwrap();
-        body
+        }
     }
 
-    #[cfg(feature= "curl")]
-    pub fn request(credential: &Credential, method: &str, query: &str, body: &str) -> String {
-
-        let localtime = Local::now().format("%s").to_string().parse::<u64>().unwrap();
-        let computed_time = localtime + OVHClient::compute_time_delta();
+    /// Sign a request for the given credential.
+    fn sign(&self, credential: &Credential, method: &str, query: &str, body: &str) -> Request {
+        let localtime = self.clock.now();
+        let computed_time = localtime + self.compute_time_delta();
         let timestamp = computed_time.to_string();
 
-        //to transfer body
-        let mut _body = body.as_bytes();
-
-        let protocol = "https://".to_string();
-        let base_path = "/1.0";
-        let url = protocol + &credential.host + &base_path + &query;
+        let url = self.base_url(credential) + &query;
         let sign = OVHClient::build_sig(&method,
                                         &url,
                                         &body,
                                         &timestamp,
-                                        credential.application_secret.as_str(),
-                                        credential.consumer_key.as_str());
-
-        // build headers
-        let mut headers = List::new();
-        headers.append(&("X-Ovh-Application: ".to_string() + &credential.application_key)).unwrap();
-        headers.append(&("X-Ovh-Timestamp: ".to_string() + &timestamp)).unwrap();
-        headers.append(&("X-Ovh-Signature: ".to_string() + &sign)).unwrap();
-        headers.append(&("X-Ovh-Consumer: ".to_string() + &credential.consumer_key)).unwrap();
-        headers.append("Accept: application/json; charset=utf-8").unwrap();
-        headers.append("User-Agent: OVH-rs/curl-rust/0.4").unwrap();
+                                        credential.application_secret.expose(),
+                                        credential.consumer_key.expose());
 
         debug!("Signature: {}", sign.to_string());
 
-        let mut client = Easy::new();
-        client.timeout(Duration::seconds(20).to_std().unwrap());
-
-        let mut response_data = Vec::new();
-        client.url(&url).unwrap();
- 
-        let resp : Result<String, String> = match method {
-            "GET" => {
-                client.http_headers(headers).unwrap();
-                client.get(true).unwrap();
-                {
-                    let mut transfer = client.transfer();
-                    transfer.write_function(|buf| {
-                        response_data.extend_from_slice(buf);
-                        Ok(buf.len())
-                    }).unwrap();
-                    transfer.perform().unwrap();
+        let headers = vec![
+            ("X-Ovh-Application".to_string(), credential.application_key.to_string()),
+            ("X-Ovh-Timestamp".to_string(), timestamp),
+            ("X-Ovh-Signature".to_string(), sign),
+            ("X-Ovh-Consumer".to_string(), credential.consumer_key.expose().to_string()),
+            ("Accept".to_string(), "application/json; charset=utf-8".to_string()),
+            ("Content-Type".to_string(), "application/json; charset=utf-8".to_string()),
+            ("User-Agent".to_string(), USER_AGENT.to_string()),
+        ];
+
+        Request {
+            method: method.to_string(),
+            url: url,
+            headers: headers,
+            body: body.to_string(),
+        }
+    }
+
+    /// Sign and send a request through the middleware chain.
+    pub fn execute(&self, method: &str, query: &str, body: &str) -> Result<Response, Error> {
+        self.execute_with(None, method, query, body)
+    }
+
+    /// Like `execute`, but sign with `consumer_key` instead of the one of
+    /// the client's `Credential`.
+    ///
+    /// One application can serve many OVH accounts this way, each with its
+    /// own consumer key, see also `tenant`.
+    pub fn execute_as(&self,
+                      consumer_key: &str,
+                      method: &str,
+                      query: &str,
+                      body: &str)
+                      -> Result<Response, Error> {
+        self.execute_with(Some(consumer_key), method, query, body)
+    }
+
+    fn execute_with(&self,
+                    consumer_key: Option<&str>,
+                    method: &str,
+                    query: &str,
+                    body: &str)
+                    -> Result<Response, Error> {
+        #[cfg(feature = "tracing")]
+        let span = trace::span(method, query);
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+        #[cfg(feature = "tracing")]
+        let start = Instant::now();
+
+        let credential = with_consumer_key(self.current_credential(), consumer_key);
+        let mut result = self.validate(method, query, body)
+            .and_then(|_| self.run(&credential, method, query, body));
+
+        if let Some(ref reloader) = self.reloader {
+            let rejected = match result {
+                Ok(ref response) => response.status == 401 || response.status == 403,
+                Err(_) => false,
+            };
+            // Retry once if the credential was rotated in the meantime
+            if rejected {
+                let reloaded = with_consumer_key(reloader.reload(), consumer_key);
+                if reloaded != credential {
+                    info!("Credential rejected, retrying with reloaded one");
+                    result = self.run(&reloaded, method, query, body);
                 }
-                let resp = String::from_utf8(response_data).unwrap();
-                Ok(resp)
             }
-            "POST" => {
-
-                headers.append("Content-Type: application/json").unwrap();
-                client.http_headers(headers).unwrap();
-
-                client.post(true).unwrap();
-                client.post_field_size(_body.len() as u64).unwrap();
-                {
-                    let mut transfer = client.transfer();
-                    transfer.read_function(|buf| {
-                        Ok(_body.read(buf).unwrap_or(0))
-                    }).unwrap();
-                    transfer.write_function(|buf| {
-                        response_data.extend_from_slice(buf);
-                        Ok(buf.len())
-                    }).unwrap();
-                    transfer.perform().unwrap();
+        }
+
+        #[cfg(feature = "tracing")]
+        trace::record(&span, &result, start.elapsed());
+        result
+    }
+
+    /// Like `execute`, but hand the response to `sink` as it is received,
+    /// e.g. to relay a large one without holding it in memory.
+    ///
+    /// Middlewares see the returned `Response`, whose body is empty. Unlike
+    /// `execute`, a call rejected by the API is not retried with a reloaded
+    /// `Credential`, its response being already handed to `sink`.
+    pub fn execute_streaming(&self,
+                             method: &str,
+                             query: &str,
+                             body: &str,
+                             sink: &mut dyn ResponseSink)
+                             -> Result<Response, Error> {
+        self.validate(method, query, body)?;
+        let mut request = self.sign(&self.current_credential(), method, query, body);
+        let sink = RefCell::new(sink);
+        let send = |request: &Request| {
+            let mut sink = sink.borrow_mut();
+            match self.plan {
+                Some(ref plan) if dry_run::is_mutating(&request.method) => {
+                    info!("Dry run, not sending {} {}", request.method, request.url);
+                    plan.lock().unwrap().push(PlannedCall::from(request));
+                    let mut response = dry_run::response();
+                    transport::deliver(&response, &mut **sink)?;
+                    response.body.clear();
+                    Ok(response)
                 }
-                let resp = String::from_utf8(response_data).unwrap();
-                Ok(resp)
+                _ => self.transport.send_streaming(request, &mut **sink),
             }
-            "PUT" => {
-                headers.append("Content-Type: application/json").unwrap();
-                client.http_headers(headers).unwrap();
-
-                client.put(true).unwrap();
-                client.post_field_size(_body.len() as u64).unwrap();
-                {
-                    let mut transfer = client.transfer();
-                    transfer.read_function(|buf| {
-                        Ok(_body.read(buf).unwrap_or(0))
-                    }).unwrap();
-                    transfer.write_function(|buf| {
-                        response_data.extend_from_slice(buf);
-                        Ok(buf.len())
-                    }).unwrap();
-                    transfer.perform().unwrap();
-                }
-                let resp = String::from_utf8(response_data).unwrap();
-                Ok(resp)
+        };
+        Next::new(&self.middlewares, &send).run(&mut request)
+    }
 
+    /// Sign a request with `credential` and run it through the middleware chain.
+    fn run(&self,
+           credential: &Credential,
+           method: &str,
+           query: &str,
+           body: &str)
+           -> Result<Response, Error> {
+        let mut request = self.sign(credential, method, query, body);
+        self.dispatch(&mut request)
+    }
+
+    /// Send a call which needs no consumer key, e.g. `POST /auth/credential`,
+    /// through the middleware chain.
+    ///
+    /// Like OVH's own clients, only the application key is sent: the call
+    /// is neither timestamped nor signed.
+    pub fn execute_unauthenticated(&self,
+                                   method: &str,
+                                   query: &str,
+                                   body: &str)
+                                   -> Result<Response, Error> {
+        self.validate(method, query, body)?;
+        let credential = self.current_credential();
+        let mut request = Request {
+            method: method.to_string(),
+            url: self.base_url(&credential) + query,
+            headers: vec![
+                ("X-Ovh-Application".to_string(), credential.application_key.to_string()),
+                ("Accept".to_string(), "application/json; charset=utf-8".to_string()),
+                ("Content-Type".to_string(), "application/json; charset=utf-8".to_string()),
+                ("User-Agent".to_string(), USER_AGENT.to_string()),
+            ],
+            body: body.to_string(),
+        };
+        self.dispatch(&mut request)
+    }
+
+    /// Run a request through the middleware chain, then send it unless
+    /// it is skipped in dry-run mode.
+    fn dispatch(&self, request: &mut Request) -> Result<Response, Error> {
+        let send = |request: &Request| match self.plan {
+            Some(ref plan) if dry_run::is_mutating(&request.method) => {
+                info!("Dry run, not sending {} {}", request.method, request.url);
+                plan.lock().unwrap().push(PlannedCall::from(request));
+                Ok(dry_run::response())
             }
-            "DELETE" => {
-                client.http_headers(headers).unwrap();
-                client.custom_request("DELETE");
-                client.nobody(true).unwrap();
-                {
-                    let mut transfer = client.transfer();
-                    transfer.write_function(|buf| {
-                        response_data.extend_from_slice(buf);
-                        Ok(buf.len())
-                    }).unwrap();
-                    transfer.perform().unwrap();
-                }
-                let resp = String::from_utf8(response_data).unwrap();
-                //to return like API
-                Ok("null".to_string())
-            }
-            _ => Err("bad method".to_string()),
+            _ => self.transport.send(request),
         };
-        resp.unwrap()
+        Next::new(&self.middlewares, &send).run(request)
+    }
+
+    /// Start a client request with given method and return the response body.
+    ///
+    /// Panics if the request cannot be sent, see `execute` for a fallible
+    /// version that also goes through the client's middlewares.
+    pub fn request(credential: &Credential, method: &str, query: &str, body: &str) -> String {
+        OVHClient::with_credential(credential.clone())
+            .execute(method, query, body)
+            .unwrap()
+            .body
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::thread;
+    use std::time::Duration;
+
     use super::OVHClient;
+    use clock::{FixedClock, OffsetClock};
+    use config::Credential;
+    use error::Error;
+    use schema::Schema;
+    use schema::tests::DOMAIN;
+    #[cfg(feature = "testing")]
+    use testing::MockServer;
+    use transport::tests::{stubbed, Stub};
+    use validate::Validator;
     extern crate serde;
     extern crate serde_json;
 
@@ -373,35 +556,45 @@ mod tests {
 
     #[test]
     fn test_remote_time() {
-        let remote_time = OVHClient::remote_time();
-        assert_eq!(true, remote_time > 0);
+        let (ovh, _) = stubbed(Credential::new_with_application("ovh-eu", "", ""));
+        assert_eq!(1466716163, ovh.remote_time());
     }
 
+    #[cfg(feature = "testing")]
     #[test]
     fn test_get() {
-        let ovh = OVHClient::new();
-        let cred = ovh.credential;
+        let server = MockServer::start(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"))
+            .unwrap();
+        server.on("GET", "/ipLoadbalancing", 200, r#"["loadbalancer-1"]"#);
+        let ovh = server.client();
 
-        let response = OVHClient::request(&cred, "GET", "/ipLoadbalancing", "");
-        // should assert after json parse
-        let deser_value: self::serde_json::Value = serde_json::from_str(&response).unwrap();
+        let response = ovh.execute("GET", "/ipLoadbalancing", "").unwrap();
+        assert_eq!(200, response.status);
+        let deser_value: self::serde_json::Value = serde_json::from_str(&response.body).unwrap();
         assert_eq!(true, deser_value.is_array());
     }
 
+    #[cfg(feature = "testing")]
     #[test]
     fn test_post() {
-        let ovh = OVHClient::new();
-        let cred = ovh.credential;
+        let server = MockServer::start(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"))
+            .unwrap();
+        let cart = r#"{"cartId": "abc", "expire": "2017-03-01T00:00:00+01:00",
+                       "description": "Default cart", "readOnly": false, "items": []}"#;
+        server.on("POST", "/order/cart", 200, cart);
+        server.on("GET", "/order/cart/abc/domain", 200, "[]");
+        server.on("PUT", "/order/cart/abc", 200, &cart.replace("Default cart",
+                                                                "a new rust cart description"));
+        server.on("POST", "/order/cart/abc/assign", 200, "null");
+        server.on("DELETE", "/order/cart/abc", 200, "null");
+        let ovh = server.client();
 
         let mut body = "{\"ovhSubsidiary\": \"FR\"}";
-        let mut response = OVHClient::request(&cred, "POST", "/order/cart", &body);
-        // should assert after json parse
+        let mut response = ovh.execute("POST", "/order/cart", &body).unwrap().body;
         let deser_value: self::serde_json::Value = serde_json::from_str(&response).unwrap();
         assert_eq!(true, deser_value.is_object());
         let obj = deser_value.as_object().unwrap();
         assert_eq!(true, obj.get("cartId").unwrap().is_string());
-        assert_eq!(true, obj.get("expire").unwrap().is_string());
-        assert_eq!(true, obj.get("description").unwrap().is_string());
         assert_eq!("Default cart",
                    obj.get("description").unwrap().as_str().unwrap());
         assert_eq!(true, obj.get("readOnly").unwrap().is_boolean());
@@ -410,39 +603,123 @@ mod tests {
         // test_get_with_query
         let cart_id = obj.get("cartId").unwrap().as_str().unwrap();
         let mut url = "/order/cart/".to_string() + cart_id + "/domain?domain=rustyrust.fr";
-
-        response = OVHClient::request(&cred, "GET", &url, "");
+        response = ovh.execute("GET", &url, "").unwrap().body;
         let deser_value: self::serde_json::Value = serde_json::from_str(&response).unwrap();
-        // should assert after json parse
         assert_eq!(true, deser_value.is_array());
 
         // test_put
         url = "/order/cart/".to_string() + cart_id;
         body = "{\"description\": \"a new rust cart description\"}";
-        response = OVHClient::request(&cred, "PUT", &url, &body);
-        // should assert after json parse
+        response = ovh.execute("PUT", &url, &body).unwrap().body;
         let deser_value: self::serde_json::Value = serde_json::from_str(&response).unwrap();
-        assert_eq!(true, deser_value.is_object());
-        let obj = deser_value.as_object().unwrap();
-        assert_eq!(true, obj.get("cartId").unwrap().is_string());
-        assert_eq!(true, obj.get("expire").unwrap().is_string());
-        assert_eq!(true, obj.get("description").unwrap().is_string());
         assert_eq!("a new rust cart description",
-                   obj.get("description").unwrap().as_str().unwrap());
-        assert_eq!(true, obj.get("readOnly").unwrap().is_boolean());
-        assert_eq!(true, obj.get("items").unwrap().is_array());
+                   deser_value.get("description").unwrap().as_str().unwrap());
 
         // test assign
         url = "/order/cart/".to_string() + cart_id + "/assign";
-        body = "";
-        response = OVHClient::request(&cred, "POST", &url, &body);
-        assert_eq!("null", response);
+        assert_eq!("null", ovh.execute("POST", &url, "").unwrap().body);
 
         // test_delete
         url = "/order/cart/".to_string() + cart_id;
-        body = "";
-        response = OVHClient::request(&cred, "DELETE", &url, &body);
-        assert_eq!("null", response);
+        assert_eq!("null", ovh.execute("DELETE", &url, "").unwrap().body);
+
+        let requests = server.requests();
+        assert_eq!(5, requests.len());
+        assert_eq!("/1.0/order/cart/abc/domain?domain=rustyrust.fr", requests[1].url);
+        assert_eq!(body, requests[2].body);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let credential = Credential::new_with_credential("ovh-eu", "ak", "as", "ck");
+        let (mut ovh, stub) = stubbed(credential);
+        stub.on("GET", "/domain", 200, "[]");
+        ovh.set_dry_run(true);
+
+        assert_eq!("[]", ovh.execute("GET", "/domain", "").unwrap().body);
+        let response = ovh.execute("POST", "/order/cart", "{}").unwrap();
+        assert_eq!("null", response.body);
+        assert_eq!(Some("true"), response.header("X-Ovh-Dry-Run"));
+        ovh.execute("DELETE", "/order/cart/abc", "").unwrap();
+
+        let sent = stub.sent.lock().unwrap();
+        assert_eq!(1, sent.len());
+        assert_eq!("https://eu.api.ovh.com/1.0/domain", sent[0].url);
+        let plan = ovh.planned_calls();
+        assert_eq!(2, plan.len());
+        assert_eq!("POST", plan[0].method);
+        assert_eq!("https://eu.api.ovh.com/1.0/order/cart", plan[0].url);
+        assert_eq!("{}", plan[0].body);
+        assert_eq!("DELETE", plan[1].method);
+    }
+
+    #[test]
+    fn test_golden_signature() {
+        let credential =
+            Credential::new_with_credential("ovh-eu", "ak", "somesecret", "fakeconsumerkey");
+        let (mut ovh, stub) = stubbed(credential);
+        ovh.set_clock(FixedClock(1466716163));
+        ovh.execute("GET", "/ipLoadbalancing", "").unwrap();
+        // a local clock 30 seconds late is corrected with OVH's time
+        ovh.set_clock(OffsetClock::new(FixedClock(1466716163), -30));
+        ovh.execute("GET", "/ipLoadbalancing", "").unwrap();
+
+        let sent = stub.sent.lock().unwrap();
+        for request in sent.iter() {
+            assert_eq!(Some("1466716163"), request.header("X-Ovh-Timestamp"));
+            assert_eq!(Some("$1$7ff04a6c8610e4f96a1c0a04dff50ed760a6b724"),
+                       request.header("X-Ovh-Signature"));
+        }
+    }
+
+    #[test]
+    fn test_reloading_follows_file() {
+        let path = env::temp_dir().join("ovh-rs-test-reloading-client.toml");
+        let write = |consumer_key: &str| {
+            let mut fd = File::create(&path).unwrap();
+            write!(fd,
+                   "[default]\nendpoint = \"ovh-eu\"\n\n[ovh-eu]\napplication_key = \"ak\"\n\
+                    application_secret = \"as\"\nconsumer_key = \"{}\"\n",
+                   consumer_key)
+                .unwrap();
+            fs::metadata(&path).unwrap().modified().unwrap()
+        };
+        let first = write("ck1");
+        let mut ovh = OVHClient::new_reloading(&path);
+        let stub = Stub::new();
+        ovh.set_transport(stub.clone());
+        ovh.execute("GET", "/me", "").unwrap();
+
+        // Rotated by another process, picked up by the next call once the
+        // modification time changes, which may take a while on coarse filesystems
+        while write("ck2") == first {
+            thread::sleep(Duration::from_millis(50));
+        }
+        ovh.execute("GET", "/me", "").unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let sent = stub.sent.lock().unwrap();
+        assert_eq!(Some("ck1"), sent[0].header("X-Ovh-Consumer"));
+        assert_eq!(Some("ck2"), sent[1].header("X-Ovh-Consumer"));
+        assert_eq!(true, sent[0].header("X-Ovh-Signature") != sent[1].header("X-Ovh-Signature"));
     }
 
+    #[test]
+    fn test_validation() {
+        let credential = Credential::new_with_credential("ovh-eu", "ak", "as", "ck");
+        let (mut ovh, stub) = stubbed(credential);
+        ovh.set_validator(Some(Validator::new(vec![Schema::parse(DOMAIN).unwrap()])));
+
+        match ovh.execute("POST", "/domain/zone/example.com/record", r#"{"target": "x"}"#) {
+            Err(Error::Validation(_)) => {}
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+        ovh.execute("GET", "/domain/zone/example.com/record", "").unwrap();
+        ovh.execute("GET", "/me", "").unwrap();
+        assert_eq!(2, stub.sent.lock().unwrap().len());
+
+        ovh.set_validator(None);
+        ovh.execute("PUT", "/domain/zone/example.com/record", "").unwrap();
+        assert_eq!(3, stub.sent.lock().unwrap().len());
+    }
 }
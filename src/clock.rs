@@ -0,0 +1,82 @@
+//! # Clock
+//!
+//! Source of the unix timestamps requests are signed with, so that
+//! signatures can be reproduced, see `OVHClient::set_clock`.
+//!
+use std::sync::Arc;
+
+use chrono::Local;
+
+pub trait Clock: Send + Sync {
+    /// Seconds since the unix epoch.
+    fn now(&self) -> u64;
+}
+
+impl<T: Clock + ?Sized> Clock for Arc<T> {
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+}
+
+/// The local system clock, used by default.
+#[derive(Debug,Clone,Copy,Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        let now = Local::now().timestamp();
+        if now < 0 { 0 } else { now as u64 }
+    }
+}
+
+/// A clock stopped at a given timestamp.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Another clock shifted by `offset` seconds, e.g. to simulate a skew.
+#[derive(Debug,Clone)]
+pub struct OffsetClock<C> {
+    pub inner: C,
+    pub offset: i64,
+}
+
+impl<C: Clock> OffsetClock<C> {
+    pub fn new(inner: C, offset: i64) -> OffsetClock<C> {
+        OffsetClock {
+            inner: inner,
+            offset: offset,
+        }
+    }
+}
+
+impl<C: Clock> Clock for OffsetClock<C> {
+    fn now(&self) -> u64 {
+        let now = self.inner.now() as i64 + self.offset;
+        if now < 0 { 0 } else { now as u64 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, FixedClock, OffsetClock, SystemClock};
+
+    #[test]
+    fn test_system_clock() {
+        // 2016-06-23, when this crate was written
+        assert_eq!(true, SystemClock.now() > 1466716163);
+    }
+
+    #[test]
+    fn test_offset_clock() {
+        assert_eq!(1466716163, FixedClock(1466716163).now());
+        assert_eq!(1466716193, OffsetClock::new(FixedClock(1466716163), 30).now());
+        assert_eq!(1466716133, OffsetClock::new(FixedClock(1466716163), -30).now());
+        assert_eq!(0, OffsetClock::new(FixedClock(10), -30).now());
+    }
+}
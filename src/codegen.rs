@@ -0,0 +1,588 @@
+//! # Codegen
+//!
+//! Generate typed Rust bindings from a `Schema`: a struct for each model,
+//! an enum for each string enum, and a trait with one method per operation,
+//! implemented for `OVHClient`.
+//!
+//! The generated code needs `#[macro_use] extern crate serde_derive;`
+//! (serde 0.9) in the crate it is part of, and calls the helpers of this
+//! module at runtime. See also the `ovh-codegen` binary.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//! use ovh::codegen::Generator;
+//!
+//! let schema = OVHClient::new().fetch_schema("domain").unwrap();
+//! let code = Generator::new().generate(&schema);
+//! ```
+//!
+extern crate serde_json;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use self::serde_json::Map;
+pub use self::serde_json::Value;
+
+use client::Response;
+use error::Error;
+use schema::{Model, Operation, ParamType, Schema, STRING_TYPES};
+
+/// Strict and reserved keywords, which are not valid identifiers.
+const KEYWORDS: [&'static str; 51] =
+    ["abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+     "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+     "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+     "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+     "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield"];
+
+/// Value of a path or query parameter: strings as is, anything else as JSON.
+pub fn param<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(value)) => value,
+        Ok(value) => value.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Percent-encode a path segment or a query string value.
+pub fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Query string of the given parameters, empty if none is set.
+pub fn query(params: &[(&str, Option<String>)]) -> String {
+    let mut query = String::new();
+    for &(name, ref value) in params {
+        if let Some(ref value) = *value {
+            query.push(if query.is_empty() { '?' } else { '&' });
+            query.push_str(name);
+            query.push('=');
+            query.push_str(&encode(value));
+        }
+    }
+    query
+}
+
+/// JSON object body, built one parameter at a time.
+#[derive(Debug,Clone,Default)]
+pub struct Body(Map<String, Value>);
+
+impl Body {
+    pub fn new() -> Body {
+        Body(Map::new())
+    }
+
+    pub fn set<T: Serialize>(&mut self, name: &str, value: &T) -> Result<(), Error> {
+        self.0.insert(name.to_string(), serde_json::to_value(value)?);
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        Value::Object(self.0.clone()).to_string()
+    }
+}
+
+/// Serialize a whole body.
+pub fn json<T: Serialize>(value: &T) -> Result<String, Error> {
+    Ok(serde_json::to_string(value)?)
+}
+
+/// Check the status of a response and deserialize its body, an empty
+/// body standing for `null`.
+pub fn decode<T: Deserialize>(response: Response) -> Result<T, Error> {
+    let response = response.error_for_status()?;
+    let body = if response.body.trim().is_empty() {
+        "null"
+    } else {
+        &response.body
+    };
+    Ok(serde_json::from_str(body)?)
+}
+
+/// Type name for a model or enum name, e.g. `DomainZoneRecord`
+/// for `domain.zone.Record`, or variant name for an enum value.
+fn camel(name: &str) -> String {
+    let mut camel = String::new();
+    for part in name.split(|c: char| !c.is_alphanumeric()).filter(|part| !part.is_empty()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            camel.extend(first.to_uppercase());
+            // `CNAME` as `Cname`
+            if part.chars().all(|c| !c.is_lowercase()) {
+                camel.push_str(&chars.as_str().to_lowercase());
+            } else {
+                camel.push_str(chars.as_str());
+            }
+        }
+    }
+    match camel.chars().next() {
+        None => "Empty".to_string(),
+        Some(first) if first.is_numeric() => format!("V{}", camel),
+        Some(_) if KEYWORDS.contains(&camel.as_str()) => format!("{}_", camel),
+        Some(_) => camel,
+    }
+}
+
+/// Field, parameter or method name, e.g. `sub_domain` for `subDomain`.
+fn snake(name: &str) -> String {
+    let mut snake = String::new();
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            if c.is_uppercase() &&
+               previous.map(|p| p.is_lowercase() || p.is_numeric()).unwrap_or(false) {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else if !snake.ends_with('_') {
+            snake.push('_');
+        }
+        previous = Some(c);
+    }
+    let snake = snake.trim_matches('_').to_string();
+    if snake.is_empty() {
+        "value".to_string()
+    } else if snake.starts_with(|c: char| c.is_numeric()) {
+        format!("n{}", snake)
+    } else if KEYWORDS.contains(&snake.as_str()) {
+        format!("{}_", snake)
+    } else {
+        snake
+    }
+}
+
+/// Name of the module generated for a schema, e.g. `dedicated_server`
+/// for `/dedicated/server`.
+pub fn module_name(schema: &Schema) -> String {
+    snake(&schema.resource_path)
+}
+
+/// Split the arguments of a generic type, e.g. `a<b,c>,d` into `a<b,c>` and `d`.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts
+}
+
+fn doc(out: &mut String, indent: &str, text: &Option<String>) {
+    if let Some(ref text) = *text {
+        for line in text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            let _ = writeln!(out, "{}/// {}", indent, line);
+        }
+    }
+}
+
+/// Generates bindings for schemas.
+#[derive(Debug,Clone)]
+pub struct Generator {
+    /// Path of this crate in the generated code, `::ovh` by default.
+    pub krate: String,
+}
+
+impl Default for Generator {
+    fn default() -> Generator {
+        Generator { krate: "::ovh".to_string() }
+    }
+}
+
+struct Context<'a> {
+    krate: &'a str,
+    /// Models by name without type parameters, e.g. `complexType.UnitAndValue`.
+    models: BTreeMap<&'a str, &'a Model>,
+}
+
+impl<'a> Context<'a> {
+    fn new(krate: &'a str, schema: &'a Schema) -> Context<'a> {
+        let models = schema.models
+            .iter()
+            .map(|(name, model)| (name.split('<').next().unwrap_or(""), model))
+            .collect();
+        Context {
+            krate: krate,
+            models: models,
+        }
+    }
+
+    fn is_enum(&self, name: &str) -> bool {
+        self.models.get(name).map(|model| model.is_enum()).unwrap_or(false)
+    }
+
+    /// Owned Rust type for an OVH type, `generics` being type parameters in scope.
+    fn rust_type(&self, kind: &str, generics: &[String]) -> String {
+        let kind = kind.trim();
+        if kind.ends_with("[]") {
+            return format!("Vec<{}>", self.rust_type(&kind[..kind.len() - 2], generics));
+        }
+        if kind.starts_with("map[string]") {
+            return format!("::std::collections::BTreeMap<String, {}>",
+                           self.rust_type(&kind["map[string]".len()..], generics));
+        }
+        if let (Some(open), true) = (kind.find('<'), kind.ends_with('>')) {
+            let args: Vec<String> = split_args(&kind[open + 1..kind.len() - 1])
+                .iter()
+                .map(|arg| self.rust_type(arg, generics))
+                .collect();
+            return format!("{}<{}>", self.rust_type(&kind[..open], generics), args.join(", "));
+        }
+        match kind {
+            "long" | "int" | "integer" => "i64".to_string(),
+            "double" | "float" => "f64".to_string(),
+            "boolean" => "bool".to_string(),
+            "void" => "()".to_string(),
+            _ if STRING_TYPES.contains(&kind) => "String".to_string(),
+            _ if generics.iter().any(|generic| generic == kind) => kind.to_string(),
+            _ if self.models.contains_key(kind) => camel(kind),
+            _ => format!("{}::codegen::Value", self.krate),
+        }
+    }
+
+    /// Argument type for an OVH type: by value if `Copy`, else borrowed.
+    fn arg_type(&self, kind: &str) -> String {
+        let owned = self.rust_type(kind, &[]);
+        match owned.as_str() {
+            "i64" | "f64" | "bool" => owned.clone(),
+            "String" => "&str".to_string(),
+            _ if self.is_enum(kind.trim()) => owned.clone(),
+            _ if owned.starts_with("Vec<") => format!("&[{}]", &owned[4..owned.len() - 1]),
+            _ => format!("&{}", owned),
+        }
+    }
+
+    fn model(&self, out: &mut String, name: &str, model: &Model) {
+        let type_name = camel(name);
+        doc(out, "", &model.description);
+        let string_enum = model.enum_type.as_ref().map(|t| t == "string").unwrap_or(true);
+        match model.enum_values {
+            Some(ref values) if string_enum => {
+                let _ = writeln!(out,
+                                 "#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,\
+                                  Deserialize)]");
+                let _ = writeln!(out, "pub enum {} {{", type_name);
+                let mut used = BTreeSet::new();
+                for value in values {
+                    let mut variant = camel(value);
+                    while !used.insert(variant.clone()) {
+                        variant.push('_');
+                    }
+                    let _ = writeln!(out, "    #[serde(rename = {:?})]", value);
+                    let _ = writeln!(out, "    {},", variant);
+                }
+                let _ = writeln!(out, "}}\n");
+            }
+            Some(_) => {
+                let kind = model.enum_type.as_ref().map(|t| t.as_str()).unwrap_or("");
+                let _ = writeln!(out, "pub type {} = {};\n", type_name, self.rust_type(kind, &[]));
+            }
+            None => {
+                let generics = model.generics.clone().unwrap_or_default();
+                let params = if generics.is_empty() {
+                    String::new()
+                } else {
+                    format!("<{}>", generics.join(", "))
+                };
+                let _ = writeln!(out, "#[derive(Debug,Clone,Serialize,Deserialize)]");
+                let _ = writeln!(out, "pub struct {}{} {{", type_name, params);
+                for (field, property) in &model.properties {
+                    let name = snake(field);
+                    let kind = property.full_type.as_ref().unwrap_or(&property.kind);
+                    let mut rust_type = self.rust_type(kind, &generics);
+                    if property.can_be_null {
+                        rust_type = format!("Option<{}>", rust_type);
+                    }
+                    doc(out, "    ", &property.description);
+                    if property.read_only {
+                        let _ = writeln!(out, "    /// Read only.");
+                    }
+                    if &name != field {
+                        let _ = writeln!(out, "    #[serde(rename = {:?})]", field);
+                    }
+                    let _ = writeln!(out, "    pub {}: {},", name, rust_type);
+                }
+                let _ = writeln!(out, "}}\n");
+            }
+        }
+    }
+
+    fn operation(&self, out: &mut String, name: &str, path: &str, operation: &Operation) {
+        let rt = format!("{}::codegen", self.krate);
+        // Locals of the generated method
+        let mut used: BTreeSet<String> = ["path", "response", "request_body"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let mut args = Vec::new();
+        let mut path_args = BTreeMap::new();
+        let mut query_args = Vec::new();
+        let mut body_args = Vec::new();
+        let mut whole_body = None;
+
+        for parameter in &operation.parameters {
+            // Set by the client
+            if parameter.param_type == ParamType::Header {
+                continue;
+            }
+            let mut arg = match parameter.name {
+                Some(ref name) => snake(name),
+                None => "body".to_string(),
+            };
+            while !used.insert(arg.clone()) {
+                arg.push('_');
+            }
+            let kind = parameter.full_type.as_ref().unwrap_or(&parameter.data_type);
+            let arg_type = self.arg_type(kind);
+            let required = parameter.required || parameter.param_type == ParamType::Path;
+            if required {
+                args.push(format!("{}: {}", arg, arg_type));
+            } else {
+                args.push(format!("{}: Option<{}>", arg, arg_type));
+            }
+            let api_name = parameter.name.clone().unwrap_or_default();
+            match (parameter.param_type, parameter.name.is_some()) {
+                (ParamType::Path, _) => {
+                    path_args.insert(api_name, arg);
+                }
+                (ParamType::Query, _) => query_args.push((api_name, arg, required)),
+                (ParamType::Body, true) => body_args.push((api_name, arg, required)),
+                (ParamType::Body, false) => whole_body = Some((arg, required)),
+                (ParamType::Header, _) => unreachable!(),
+            }
+        }
+
+        let mut template = String::new();
+        let mut format_args = Vec::new();
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            template.push('/');
+            if segment.starts_with('{') && segment.ends_with('}') {
+                template.push_str("{}");
+                let api_name = &segment[1..segment.len() - 1];
+                let arg = match path_args.get(api_name).cloned() {
+                    Some(arg) => arg,
+                    // Missing from the parameters
+                    None => {
+                        let mut arg = snake(api_name);
+                        while !used.insert(arg.clone()) {
+                            arg.push('_');
+                        }
+                        args.push(format!("{}: &str", arg));
+                        arg
+                    }
+                };
+                format_args.push(format!("{}::encode(&{}::param(&{}))", rt, rt, arg));
+            } else {
+                template.push_str(segment);
+            }
+        }
+        template.push_str("{}");
+        let query_params: Vec<String> = query_args.iter()
+            .map(|&(ref api_name, ref arg, required)| {
+                if required {
+                    format!("({:?}, Some({}::param(&{})))", api_name, rt, arg)
+                } else {
+                    format!("({:?}, {}.map(|v| {}::param(&v)))", api_name, arg, rt)
+                }
+            })
+            .collect();
+        format_args.push(format!("{}::query(&[{}])", rt, query_params.join(", ")));
+
+        let response_type = self.rust_type(&operation.response_type, &[]);
+        let mut description = operation.description.clone();
+        if let Some(ref status) = operation.api_status {
+            if status.value.starts_with("DEPRECATED") {
+                description = Some(format!("{}\nDeprecated: {}",
+                                           description.unwrap_or_default(),
+                                           status.description.clone().unwrap_or_default()));
+            }
+        }
+        doc(out, "    ", &description);
+        let _ = writeln!(out, "    ///\n    /// `{} {}`", operation.http_method, path);
+        let _ = writeln!(out,
+                         "    fn {}(&self{}{}) -> Result<{}, {}::Error> {{",
+                         name,
+                         if args.is_empty() { "" } else { ", " },
+                         args.join(", "),
+                         response_type,
+                         self.krate);
+        let _ = writeln!(out,
+                         "        let path = format!({:?}, {});",
+                         template,
+                         format_args.join(", "));
+        let body = match whole_body {
+            Some((ref arg, true)) => format!("{}::json(&{})?", rt, arg),
+            Some((ref arg, false)) => {
+                format!("match {} {{ Some(body) => {}::json(&body)?, None => String::new() }}",
+                        arg,
+                        rt)
+            }
+            None if body_args.is_empty() => "String::new()".to_string(),
+            None => {
+                let _ = writeln!(out, "        let mut request_body = {}::Body::new();", rt);
+                for &(ref api_name, ref arg, required) in &body_args {
+                    if required {
+                        let _ = writeln!(out,
+                                         "        request_body.set({:?}, &{})?;",
+                                         api_name,
+                                         arg);
+                    } else {
+                        let _ = writeln!(out,
+                                         "        if let Some(value) = {} {{\n            \
+                                          request_body.set({:?}, &value)?;\n        }}",
+                                         arg,
+                                         api_name);
+                    }
+                }
+                "request_body.to_json()".to_string()
+            }
+        };
+        let _ = writeln!(out, "        let body = {};", body);
+        let execute = if operation.no_authentication {
+            "execute_unauthenticated"
+        } else {
+            "execute"
+        };
+        let _ = writeln!(out,
+                         "        let response = self.ovh_client().{}({:?}, &path, &body)?;",
+                         execute,
+                         operation.http_method);
+        let _ = writeln!(out, "        {}::decode(response)", rt);
+        let _ = writeln!(out, "    }}\n");
+    }
+}
+
+impl Generator {
+    pub fn new() -> Generator {
+        Generator::default()
+    }
+
+    /// Rust source of the bindings for `schema`.
+    pub fn generate(&self, schema: &Schema) -> String {
+        let context = Context::new(&self.krate, schema);
+        let mut out = String::new();
+        let _ = writeln!(out,
+                         "// Generated by ovh-codegen from the schema of {}, do not edit.\n",
+                         schema.resource_path);
+
+        for (name, model) in &schema.models {
+            context.model(&mut out, name.split('<').next().unwrap_or(""), model);
+        }
+
+        let trait_name = format!("{}Api", camel(&schema.resource_path));
+        let _ = writeln!(out, "/// Operations of `{}`.", schema.resource_path);
+        let _ = writeln!(out, "pub trait {} {{", trait_name);
+        let _ = writeln!(out, "    fn ovh_client(&self) -> &{}::OVHClient;\n", self.krate);
+        let mut used = BTreeSet::new();
+        for api in &schema.apis {
+            for operation in &api.operations {
+                let mut name = snake(&format!("{} {}", operation.http_method, api.path));
+                while !used.insert(name.clone()) {
+                    name.push('_');
+                }
+                context.operation(&mut out, &name, &api.path, operation);
+            }
+        }
+        out.pop();
+        let _ = writeln!(out, "}}\n");
+        let _ = writeln!(out, "impl {} for {}::OVHClient {{", trait_name, self.krate);
+        let _ = writeln!(out, "    fn ovh_client(&self) -> &{}::OVHClient {{", self.krate);
+        let _ = writeln!(out, "        self\n    }}\n}}");
+        out
+    }
+}
+
+/// Output of `Generator` for the `DOMAIN` schema fixture, to check that it compiles.
+#[cfg(test)]
+#[allow(dead_code)]
+mod generated {
+    include!("../testdata/domain_api.rs");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{camel, encode, query, snake, Context, Generator};
+    use schema::Schema;
+    use schema::tests::DOMAIN;
+
+    #[test]
+    fn test_names() {
+        assert_eq!("DomainZoneRecord", camel("domain.zone.Record"));
+        assert_eq!("PendingValidation", camel("pendingValidation"));
+        assert_eq!("V2016", camel("2016"));
+        assert_eq!("Cname", camel("CNAME"));
+        assert_eq!("sub_domain", snake("subDomain"));
+        assert_eq!("get_domain_zone_zone_name_record",
+                   snake("get /domain/zone/{zoneName}/record"));
+        assert_eq!("type_", snake("type"));
+        assert_eq!("while_", snake("while"));
+        assert_eq!("async_", snake("async"));
+        assert_eq!("Self_", camel("self"));
+    }
+
+    #[test]
+    fn test_types() {
+        let schema = Schema::parse(DOMAIN).unwrap();
+        let context = Context::new("::ovh", &schema);
+        assert_eq!("Vec<i64>", context.rust_type("long[]", &[]));
+        assert_eq!("ZoneNamedResolutionFieldTypeEnum",
+                   context.rust_type("zone.NamedResolutionFieldTypeEnum", &[]));
+        assert_eq!("::ovh::codegen::Value", context.rust_type("other.Model", &[]));
+        assert_eq!("&str", context.arg_type("string"));
+        assert_eq!("&DomainZoneRecord", context.arg_type("domain.zone.Record"));
+        assert_eq!("ZoneNamedResolutionFieldTypeEnum",
+                   context.arg_type("zone.NamedResolutionFieldTypeEnum"));
+    }
+
+    #[test]
+    fn test_query() {
+        assert_eq!("", query(&[("fieldType", None)]));
+        assert_eq!("?fieldType=A&subDomain=a%20b",
+                   query(&[("fieldType", Some("A".to_string())),
+                           ("subDomain", Some("a b".to_string()))]));
+        assert_eq!("1.2.3.0%2F24", encode("1.2.3.0/24"));
+    }
+
+    #[test]
+    fn test_generated_domain() {
+        // The generated code is compiled as the `generated` module below
+        let generator = Generator { krate: String::new() };
+        let code = generator.generate(&Schema::parse(DOMAIN).unwrap());
+        assert_eq!(include_str!("../testdata/domain_api.rs"), code);
+    }
+
+    #[test]
+    fn test_generate() {
+        let code = Generator::new().generate(&Schema::parse(DOMAIN).unwrap());
+        assert_eq!(true, code.contains("pub enum ZoneNamedResolutionFieldTypeEnum {"));
+        assert_eq!(true, code.contains("    #[serde(rename = \"AAAA\")]\n    Aaaa,"));
+        assert_eq!(true, code.contains("    #[serde(rename = \"subDomain\")]\n    \
+                                        pub sub_domain: Option<String>,"));
+        assert_eq!(true, code.contains("pub trait DomainApi {"));
+        assert_eq!(true,
+                   code.contains("    fn get_domain_zone_zone_name_record(&self, zone_name: &str, \
+                                  field_type: Option<ZoneNamedResolutionFieldTypeEnum>, \
+                                  sub_domain: Option<&str>) -> Result<Vec<i64>, ::ovh::Error> {"));
+        assert_eq!(true, code.contains("        request_body.set(\"target\", &target)?;"));
+        assert_eq!(true, code.contains("impl DomainApi for ::ovh::OVHClient {"));
+    }
+}
@@ -0,0 +1,178 @@
+//! # Collection
+//!
+//! Most OVH resources are enumerated by fetching a collection of ids
+//! then each `/{id}`. This module runs the second step on a bounded
+//! pool of worker threads.
+//!
+extern crate serde;
+extern crate serde_json;
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use self::serde::Deserialize;
+use self::serde_json::Value;
+
+use client::OVHClient;
+use error::Error;
+
+/// Iterator over the details of a collection's items.
+///
+/// Items are yielded as soon as they are fetched, so the order
+/// does not follow the order of the collection.
+pub struct Details<T> {
+    receiver: Receiver<Result<T, Error>>,
+}
+
+impl<T> Iterator for Details<T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Result<T, Error>> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl OVHClient {
+    /// GET the collection at `path`, then GET the details of each item
+    /// with at most `concurrency` requests in flight.
+    ///
+    /// A query string on `path` (e.g. `?fieldType=A`) filters the
+    /// collection and is not forwarded to the item requests.
+    pub fn fetch_all<T>(&self, path: &str, concurrency: usize) -> Result<Details<T>, Error>
+        where T: Deserialize + Send + 'static
+    {
+        let response = self.execute("GET", path, "")?.error_for_status()?;
+        let ids: Vec<Value> = serde_json::from_str(&response.body)?;
+        let workers = cmp::max(1, cmp::min(concurrency, ids.len()));
+        let queue: VecDeque<String> = ids.iter().map(|id| item_path(path, id)).collect();
+        let queue = Arc::new(Mutex::new(queue));
+
+        let (sender, receiver) = channel();
+        for _ in 0..workers {
+            let queue = queue.clone();
+            let sender = sender.clone();
+            let client = self.clone();
+            thread::spawn(move || loop {
+                let next = queue.lock().unwrap().pop_front();
+                let item = match next {
+                    Some(item) => item,
+                    None => break,
+                };
+                let detail = client.execute("GET", &item, "")
+                    .and_then(|response| response.error_for_status())
+                    .and_then(|response| serde_json::from_str(&response.body).map_err(Error::from));
+                if sender.send(detail).is_err() {
+                    // Iterator was dropped, nobody is listening anymore
+                    break;
+                }
+            });
+        }
+
+        Ok(Details { receiver: receiver })
+    }
+}
+
+/// Build the path of a collection item from its id.
+fn item_path(collection: &str, id: &Value) -> String {
+    let base = collection.split('?').next().unwrap_or("");
+    let id = match *id {
+        Value::String(ref s) => s.clone(),
+        ref other => other.to_string(),
+    };
+    // ids such as IP blocks contain slashes that must not split the path
+    let id = id.replace("/", "%2F");
+    format!("{}/{}", base.trim_end_matches('/'), id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::item_path;
+    use super::serde_json::Value;
+    use client::OVHClient;
+    use config::Credential;
+    use dns::tests::client;
+    use error::Error;
+    use transport::tests::Stub;
+
+    fn slow(routes: Vec<(&'static str, &'static str)>) -> (OVHClient, Arc<Stub>) {
+        let stub = Stub::slow(Duration::from_millis(20));
+        for (path, body) in routes {
+            stub.on("GET", path, 200, body);
+        }
+        let mut client =
+            OVHClient::with_credential(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"));
+        client.set_transport(stub.clone());
+        (client, stub)
+    }
+
+    #[test]
+    fn test_fetch_all() {
+        let (client, api) = slow(vec![("/zone/example.com/record", "[1, 2, 3, 4]"),
+                                      ("/record/1", r#"{"id": 1}"#),
+                                      ("/record/2", r#"{"id": 2}"#),
+                                      ("/record/4", r#"{"id": 4}"#)]);
+        let details = client.fetch_all::<Value>("/domain/zone/example.com/record", 2).unwrap();
+        let (found, missing): (Vec<_>, Vec<_>) = details.partition(|detail| detail.is_ok());
+
+        let mut ids: Vec<u64> = found.into_iter()
+            .map(|detail| detail.unwrap()["id"].as_u64().unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(vec![1, 2, 4], ids);
+        assert_eq!(1, missing.len());
+        match missing[0] {
+            Err(Error::Api { status, .. }) => assert_eq!(404, status),
+            ref other => panic!("expected a 404, got {:?}", other),
+        }
+        assert_eq!(5, api.sent.lock().unwrap().len());
+        assert_eq!(true, api.max_in_flight() <= 2);
+    }
+
+    #[test]
+    fn test_fetch_all_missing_collection() {
+        let (client, _) = client(vec![]);
+        match client.fetch_all::<Value>("/domain/zone/unknown.com/record", 2) {
+            Err(Error::Api { status, .. }) => assert_eq!(404, status),
+            Err(other) => panic!("expected a 404, got {:?}", other),
+            Ok(_) => panic!("expected a 404"),
+        }
+    }
+
+    #[test]
+    fn test_fetch_all_dropped() {
+        let (client, api) = slow(vec![("/record", "[1, 2, 3, 4, 5, 6]"), ("", "{}")]);
+        let mut details = client.fetch_all::<Value>("/domain/zone/example.com/record", 1)
+            .unwrap();
+        details.next().unwrap().unwrap();
+        drop(details);
+
+        // Long enough for the worker to fetch every item if it did not stop
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(true, api.sent.lock().unwrap().len() < 7);
+    }
+
+    #[test]
+    fn test_item_path_numeric_id() {
+        let path = item_path("/domain/zone/example.com/record", &Value::from(42u64));
+        assert_eq!("/domain/zone/example.com/record/42", path);
+    }
+
+    #[test]
+    fn test_item_path_string_id() {
+        let path = item_path("/ip/", &Value::from("1.2.3.4/32"));
+        assert_eq!("/ip/1.2.3.4%2F32", path);
+    }
+
+    #[test]
+    fn test_item_path_drops_query() {
+        let path = item_path("/domain/zone/example.com/record?fieldType=A", &Value::from(7u64));
+        assert_eq!("/domain/zone/example.com/record/7", path);
+    }
+}
@@ -6,102 +6,227 @@
 //!
 extern crate toml;
 
-use std::fs::File;
-use std::path::Path;
-use std::io::{Read, Error};
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::io::{Read, Write, Error, ErrorKind};
+
+use encrypted::read_encrypted_file;
+use secret::Secret;
+use zeroize::Zeroize;
 
 const DEFAULT_CONFIG_PATH: &'static str = "Config.toml";
 
 /// OVH API application credentials, including application key, application secret key,
 /// consumer key, a temporary access token with access control to user API.
-#[derive(Debug,Clone)]
+///
+/// The application secret and the consumer key are redacted from `Debug` output.
+#[derive(Debug,Clone,PartialEq)]
 pub struct Credential {
     path: Option<String>,
-    toml: Option<toml::Value>,
+    endpoint: String,
     pub host: String,
     pub application_key: String,
-    pub application_secret: String,
-    pub consumer_key: String,
+    pub application_secret: Secret,
+    pub consumer_key: Secret,
 }
 
 /// Utility fonction to read toml file by path
 /// Currently only considere api on subsidiary : ovh-eu, ovh-ca.
-fn read_from_path<'a, P: AsRef<Path>>(owner: &'a mut String,
-                                      path: P)
-                                      -> Result<(String, toml::Value), Error> {
-    let mut fd = match File::open(path) {
-        Err(_) => panic!("Cannot open given path"),
-        Ok(fd) => fd,
-    };
-    match fd.read_to_string(owner) {
-        Err(_) => panic!("Cannot read file"),
-        Ok(s) => s,
-    };
-    let mut parser = toml::Parser::new(owner);
+fn read_from_path<P: AsRef<Path>>(path: P) -> Result<Credential, Error> {
+    let path_str = path.as_ref().to_string_lossy().into_owned();
+    let mut content = String::new();
+    File::open(path)?.read_to_string(&mut content)?;
+    let credential = parse_config(&content, Some(path_str));
+    content.zeroize();
+    credential
+}
+
+/// Replace the file at `path` with `content`, only readable by its owner on unix.
+///
+/// Content goes to a temporary file first so that readers never see
+/// a partially written file.
+pub(crate) fn write_private(path: &Path, content: &[u8]) -> Result<(), Error> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    {
+        let mut fd = options.open(&tmp)?;
+        fd.write_all(content)?;
+        fd.sync_all()?;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&tmp, fs::Permissions::from_mode(0o600))?;
+    }
+    fs::rename(&tmp, path)
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Utility fonction to extract the credentials
+/// of the default endpoint from toml content.
+fn parse_config(content: &str, path: Option<String>) -> Result<Credential, Error> {
+    parse_profile(content, path, None)
+}
+
+/// Extract the credentials of the `profile` endpoint section,
+/// or of the default endpoint if `None`.
+fn parse_profile(content: &str,
+                 path: Option<String>,
+                 profile: Option<&str>)
+                 -> Result<Credential, Error> {
+    let mut parser = toml::Parser::new(content);
     let toml = match parser.parse() {
-        None => panic!("Cannot parse toml content"),
+        None => return Err(invalid("Cannot parse toml content")),
         Some(_toml) => _toml,
     };
-    let endpoint: toml::Value = toml.get("default")
-        .unwrap()
-        .lookup("endpoint")
-        .unwrap()
-        .clone();
-    let _endpoint: &str = &endpoint.as_str().unwrap();
-    let host = endpoint2host(_endpoint);
-
-    Ok((host, toml.get(_endpoint).unwrap().clone()))
+    let endpoint = match profile {
+        Some(profile) => profile,
+        None => {
+            toml.get("default")
+                .and_then(|default| default.lookup("endpoint"))
+                .and_then(|endpoint| endpoint.as_str())
+                .ok_or_else(|| invalid("Missing default endpoint"))?
+        }
+    };
+    let auth = toml.get(endpoint).ok_or_else(|| invalid("Missing endpoint section"))?;
+    let field = |name: &str| auth.lookup(name).and_then(|value| value.as_str());
+
+    let app_key = field("application_key").ok_or_else(|| invalid("Missing application_key"))?;
+    let app_secret = field("application_secret")
+        .ok_or_else(|| invalid("Missing application_secret"))?;
+    // Not known yet until the consumer key validation flow is done
+    let cons_key = field("consumer_key").unwrap_or("");
+
+    Ok(Credential {
+        path: path,
+        endpoint: String::from(endpoint),
+        host: endpoint2host(endpoint),
+        application_key: String::from(app_key),
+        application_secret: Secret::new(app_secret),
+        consumer_key: Secret::new(cons_key),
+    })
 }
 
 impl Credential {
     /// Initialize a new `Credential` from default path a App Key, App secret, Consumer token.
     pub fn new() -> Credential {
-        let toml = &mut String::new();
-        let (host, auth): (String, toml::Value) =
-            match read_from_path(toml, DEFAULT_CONFIG_PATH.to_owned()) {
-                Err(_) => panic!("Could not read auth"),
-                Ok(_auth) => _auth,
-            };
-        let r_app_key = auth.lookup("application_key").unwrap().clone();
-        let app_key = String::from(r_app_key.as_str().unwrap().clone());
-        let r_app_secret = auth.lookup("application_secret").unwrap().clone();
-        let app_secret = String::from(r_app_secret.as_str().unwrap().clone());
-        let r_cons_key = auth.lookup("consumer_key").unwrap().clone();
-        let cons_key = String::from(r_cons_key.as_str().unwrap().clone());
-
-        Credential {
-            toml: Some(auth),
-            path: Some(DEFAULT_CONFIG_PATH.to_owned()),
-            host: host,
-            application_key: app_key,
-            application_secret: app_secret,
-            consumer_key: cons_key,
-        }
+        Credential::new_from_file(DEFAULT_CONFIG_PATH)
     }
 
     /// Initialize a new `Credential` from given path a App Key, App secret, Consumer token.
     pub fn new_from_file<P: AsRef<Path>>(path: P) -> Credential {
+        match read_from_path(path) {
+            Err(_) => panic!("Could not read auth"),
+            Ok(_auth) => _auth,
+        }
+    }
 
-        let toml = &mut String::new();
-        let (host, auth): (String, toml::Value) = match read_from_path(toml, path) {
+    /// Load a `Credential` from given path, without panicking
+    /// on a missing or malformed file.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Credential, Error> {
+        read_from_path(path)
+    }
+
+    /// Load the `Credential` of the `profile` endpoint section of the
+    /// file at `path`, e.g. `ovh-ca`, instead of the default one.
+    pub fn load_profile<P: AsRef<Path>>(path: P, profile: &str) -> Result<Credential, Error> {
+        let path_str = path.as_ref().to_string_lossy().into_owned();
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        let credential = parse_profile(&content, Some(path_str), Some(profile));
+        content.zeroize();
+        credential
+    }
+
+    /// Initialize a new `Credential` from a config file encrypted
+    /// with `encrypted::write_encrypted_file`.
+    ///
+    /// `passphrase` can be the content of a key file, see `encrypted::read_key_file`.
+    pub fn new_from_encrypted_file<P: AsRef<Path>>(path: P, passphrase: &[u8]) -> Credential {
+        match Credential::load_from_encrypted_file(path, passphrase) {
             Err(_) => panic!("Could not read auth"),
             Ok(_auth) => _auth,
-        };
-        let r_app_key = auth.lookup("application_key").unwrap().clone();
-        let app_key = String::from(r_app_key.as_str().unwrap().clone());
-        let r_app_secret = auth.lookup("application_secret").unwrap().clone();
-        let app_secrets = String::from(r_app_secret.as_str().unwrap().clone());
-        let r_cons_key = auth.lookup("consumer_key").unwrap().clone();
-        let cons_key = String::from(r_cons_key.as_str().unwrap().clone());
+        }
+    }
 
-        Credential {
-            toml: Some(auth),
-            path: Some("".to_string()),
-            host: host,
-            application_key: app_key,
-            application_secret: app_secrets,
-            consumer_key: cons_key,
+    /// Load a `Credential` from an encrypted config file, without
+    /// panicking on a wrong passphrase or a missing or malformed file.
+    pub fn load_from_encrypted_file<P: AsRef<Path>>(path: P,
+                                                    passphrase: &[u8])
+                                                    -> Result<Credential, Error> {
+        let mut content = read_encrypted_file(path, passphrase)?;
+        let parsed = parse_config(&content, None);
+        content.zeroize();
+        parsed
+    }
+
+    /// Path of the file this `Credential` was read from, if any.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_ref().map(|path| path.as_str())
+    }
+
+    /// Endpoint name, e.g. `ovh-eu`.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Write this `Credential` into the section of its endpoint
+    /// in the toml file at `path`.
+    ///
+    /// Other sections and keys are preserved (comments are not), and
+    /// `[default]` is only added when missing. The file is created if
+    /// needed and, on unix, only readable by its owner.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut content = String::new();
+        match File::open(path) {
+            Ok(mut fd) => {
+                fd.read_to_string(&mut content)?;
+            }
+            Err(ref err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
         }
+        let parsed = toml::Parser::new(&content).parse();
+        content.zeroize();
+        // Never clobber a file we cannot understand
+        let mut table = parsed.ok_or_else(|| invalid("Cannot parse toml content"))?;
+
+        if !table.contains_key("default") {
+            let mut default = toml::Table::new();
+            default.insert("endpoint".to_string(), toml::Value::String(self.endpoint.clone()));
+            table.insert("default".to_string(), toml::Value::Table(default));
+        }
+        {
+            let section = table.entry(self.endpoint.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            let section = match *section {
+                toml::Value::Table(ref mut section) => section,
+                _ => return Err(invalid("Endpoint is not a section")),
+            };
+            section.insert("application_key".to_string(),
+                           toml::Value::String(self.application_key.clone()));
+            section.insert("application_secret".to_string(),
+                           toml::Value::String(self.application_secret.expose().to_string()));
+            section.insert("consumer_key".to_string(),
+                           toml::Value::String(self.consumer_key.expose().to_string()));
+        }
+
+        let mut output = toml::Value::Table(table).to_string();
+        let written = write_private(path, output.as_bytes());
+        output.zeroize();
+        written
     }
 
     /// Initialize a new `Credential` from given an App Key and App secret.
@@ -111,12 +236,12 @@ impl Credential {
                                 -> Credential {
         let host = endpoint2host(endpoint);
         Credential {
-            toml: None,
             path: None,
-            host: String::from(host),
+            endpoint: String::from(endpoint),
+            host: host,
             application_key: String::from(application_key),
-            application_secret: String::from(application_secret),
-            consumer_key: String::from(""),
+            application_secret: Secret::new(application_secret),
+            consumer_key: Secret::new(""),
         }
     }
 
@@ -128,16 +253,22 @@ impl Credential {
                                -> Credential {
         let host = endpoint2host(endpoint);
         Credential {
-            toml: None,
             path: None,
+            endpoint: String::from(endpoint),
             host: host,
             application_key: String::from(application_key),
-            application_secret: String::from(application_secret),
-            consumer_key: String::from(consumer_key),
+            application_secret: Secret::new(application_secret),
+            consumer_key: Secret::new(consumer_key),
         }
     }
 }
 
+/// Whether `endpoint` names a known API endpoint, e.g. `ovh-ca`, rather
+/// than any other section of a config file.
+pub fn is_endpoint(endpoint: &str) -> bool {
+    endpoint2host(endpoint) != "api.ovh.com"
+}
+
 fn endpoint2host(endpoint: &str) -> String {
     match endpoint.as_ref() {
         "ovh-ca" => "ca.api.ovh.com".to_string(), // OVH North America
@@ -157,7 +288,12 @@ fn endpoint2host(endpoint: &str) -> String {
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::{ErrorKind, Read, Write};
     use super::Credential;
+    use secret::Secret;
+    use encrypted::write_encrypted_file;
 
     #[test]
     fn test_application_key() {
@@ -170,14 +306,14 @@ mod tests {
     fn test_application_secret() {
         let cred = Credential::new_from_file("Config.toml.dist");
         let res = cred.application_secret;
-        assert_eq!("as", res);
+        assert_eq!("as", res.expose());
     }
 
     #[test]
     fn test_consumer_key() {
         let cred = Credential::new_from_file("Config.toml.dist");
         let res = cred.consumer_key;
-        assert_eq!("ck", res);
+        assert_eq!("ck", res.expose());
     }
 
     #[test]
@@ -212,4 +348,91 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_encrypted_file() {
+        let mut content = String::new();
+        File::open("Config.toml.dist").unwrap().read_to_string(&mut content).unwrap();
+        let path = env::temp_dir().join("ovh-rs-test-config.toml.enc");
+        write_encrypted_file(&path, &content, b"passphrase").unwrap();
+
+        let cred = Credential::new_from_encrypted_file(&path, b"passphrase");
+        fs::remove_file(&path).unwrap();
+        assert_eq!("eu.api.ovh.com", cred.host);
+        assert_eq!("ak", cred.application_key);
+        assert_eq!("as", cred.application_secret.expose());
+        assert_eq!("ck", cred.consumer_key.expose());
+    }
+
+    #[test]
+    fn test_encrypted_file_wrong_passphrase() {
+        let path = env::temp_dir().join("ovh-rs-test-config-wrong.toml.enc");
+        write_encrypted_file(&path, "[default]\nendpoint = \"ovh-eu\"\n", b"passphrase")
+            .unwrap();
+        let loaded = Credential::load_from_encrypted_file(&path, b"wrong");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(ErrorKind::InvalidData, loaded.unwrap_err().kind());
+    }
+
+    #[test]
+    fn test_save_to_file() {
+        let path = env::temp_dir().join("ovh-rs-test-save.toml");
+        fs::copy("Config.toml.dist", &path).unwrap();
+        {
+            let mut fd = fs::OpenOptions::new().append(true).open(&path).unwrap();
+            fd.write_all(b"\n[ovh-ca]\napplication_key = \"other\"\n").unwrap();
+        }
+
+        let mut cred = Credential::new_from_file(&path);
+        cred.consumer_key = Secret::new("validated");
+        cred.save_to_file(&path).unwrap();
+
+        let saved = Credential::new_from_file(&path);
+        assert_eq!("validated", saved.consumer_key.expose());
+        assert_eq!("as", saved.application_secret.expose());
+        let mut content = String::new();
+        File::open(&path).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(true, content.contains("[ovh-ca]"));
+        assert_eq!(true, content.contains("\"other\""));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_save_to_new_file() {
+        let path = env::temp_dir().join("ovh-rs-test-save-new.toml");
+        let _ = fs::remove_file(&path);
+        let cred = Credential::new_with_credential("ovh-ca", "ak", "as", "ck");
+        cred.save_to_file(&path).unwrap();
+
+        let saved = Credential::new_from_file(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!("ovh-ca", saved.endpoint());
+        assert_eq!("ca.api.ovh.com", saved.host);
+        assert_eq!("ck", saved.consumer_key.expose());
+    }
+
+    #[test]
+    fn test_load_profile() {
+        let content = "[default]\nendpoint = \"ovh-eu\"\n\n\
+                       [ovh-eu]\napplication_key = \"ak\"\napplication_secret = \"as\"\n\n\
+                       [ovh-ca]\napplication_key = \"ak-ca\"\napplication_secret = \"as-ca\"\n";
+        let path = env::temp_dir().join("ovh-rs-test-profile.toml");
+        File::create(&path).unwrap().write_all(content.as_bytes()).unwrap();
+
+        let cred = Credential::load_profile(&path, "ovh-ca").unwrap();
+        assert_eq!("ovh-ca", cred.endpoint());
+        assert_eq!("ca.api.ovh.com", cred.host);
+        assert_eq!("ak-ca", cred.application_key);
+        assert_eq!(true, Credential::load_profile(&path, "kimsufi-eu").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let cred = Credential::new_with_credential("ovh-eu", "ak", "topsecret", "myconsumer");
+        let debug = format!("{:?}", cred);
+        assert_eq!(false, debug.contains("topsecret"));
+        assert_eq!(false, debug.contains("myconsumer"));
+        assert_eq!(true, debug.contains("ak"));
+    }
+
 }
@@ -0,0 +1,391 @@
+//! # DNS
+//!
+//! Typed access to the DNS zones hosted by OVH: records of
+//! `/domain/zone/{zoneName}/record` and zone refresh.
+//!
+//! Record changes are only served once the zone is refreshed.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//! use ovh::dns::{FieldType, NewRecord, RecordFilter};
+//!
+//! let client = OVHClient::new();
+//! let filter = RecordFilter { field_type: Some(FieldType::A), ..RecordFilter::default() };
+//! for record in client.records("example.com", &filter).unwrap() {
+//!     println!("{} {} {}", record.sub_domain, record.field_type, record.target);
+//! }
+//! client.create_record("example.com", &NewRecord::new(FieldType::A, "www", "192.0.2.1")).unwrap();
+//! client.refresh_zone("example.com").unwrap();
+//! ```
+//!
+extern crate serde;
+extern crate serde_json;
+
+use std::fmt;
+
+use self::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use client::OVHClient;
+use codegen::{decode, encode, query};
+use error::Error;
+
+/// Records fetched at once by `records`.
+const CONCURRENCY: usize = 8;
+
+/// Type of a record, e.g. `A` or `MX`.
+#[derive(Debug,Clone,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub enum FieldType {
+    A,
+    AAAA,
+    CAA,
+    CNAME,
+    DKIM,
+    DMARC,
+    DNAME,
+    LOC,
+    MX,
+    NAPTR,
+    NS,
+    PTR,
+    SPF,
+    SRV,
+    SSHFP,
+    TLSA,
+    TXT,
+    /// Any type this crate does not know about yet.
+    Other(String),
+}
+
+impl FieldType {
+    /// Map a type as returned by the API.
+    pub fn from_api(field_type: &str) -> FieldType {
+        match field_type {
+            "A" => FieldType::A,
+            "AAAA" => FieldType::AAAA,
+            "CAA" => FieldType::CAA,
+            "CNAME" => FieldType::CNAME,
+            "DKIM" => FieldType::DKIM,
+            "DMARC" => FieldType::DMARC,
+            "DNAME" => FieldType::DNAME,
+            "LOC" => FieldType::LOC,
+            "MX" => FieldType::MX,
+            "NAPTR" => FieldType::NAPTR,
+            "NS" => FieldType::NS,
+            "PTR" => FieldType::PTR,
+            "SPF" => FieldType::SPF,
+            "SRV" => FieldType::SRV,
+            "SSHFP" => FieldType::SSHFP,
+            "TLSA" => FieldType::TLSA,
+            "TXT" => FieldType::TXT,
+            other => FieldType::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            FieldType::A => "A",
+            FieldType::AAAA => "AAAA",
+            FieldType::CAA => "CAA",
+            FieldType::CNAME => "CNAME",
+            FieldType::DKIM => "DKIM",
+            FieldType::DMARC => "DMARC",
+            FieldType::DNAME => "DNAME",
+            FieldType::LOC => "LOC",
+            FieldType::MX => "MX",
+            FieldType::NAPTR => "NAPTR",
+            FieldType::NS => "NS",
+            FieldType::PTR => "PTR",
+            FieldType::SPF => "SPF",
+            FieldType::SRV => "SRV",
+            FieldType::SSHFP => "SSHFP",
+            FieldType::TLSA => "TLSA",
+            FieldType::TXT => "TXT",
+            FieldType::Other(ref other) => other,
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Serialize for FieldType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl Deserialize for FieldType {
+    fn deserialize<D: Deserializer>(deserializer: D) -> Result<FieldType, D::Error> {
+        let field_type = <String as Deserialize>::deserialize(deserializer)?;
+        Ok(FieldType::from_api(&field_type))
+    }
+}
+
+/// `null` as the default value, e.g. `""` for a record of the zone apex.
+pub(crate) fn null_as_default<T, D>(deserializer: D) -> Result<T, D::Error>
+    where T: Deserialize + Default,
+          D: Deserializer
+{
+    Ok(<Option<T> as Deserialize>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// A zone, as returned by `GET /domain/zone/{zoneName}`.
+#[derive(Debug,Clone,PartialEq,Deserialize)]
+pub struct Zone {
+    pub name: String,
+    #[serde(rename = "nameServers", default)]
+    pub name_servers: Vec<String>,
+    #[serde(rename = "dnssecSupported", default)]
+    pub dnssec_supported: bool,
+    #[serde(rename = "hasDnsAnycast", default)]
+    pub has_dns_anycast: bool,
+    #[serde(rename = "lastUpdate", default)]
+    pub last_update: Option<String>,
+}
+
+/// A record of a zone.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct Record {
+    pub id: u64,
+    pub zone: String,
+    #[serde(rename = "fieldType")]
+    pub field_type: FieldType,
+    /// Empty for the zone apex.
+    #[serde(rename = "subDomain", default, deserialize_with = "null_as_default")]
+    pub sub_domain: String,
+    pub target: String,
+    /// 0 for the zone's default TTL.
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub ttl: u32,
+}
+
+/// A record to create with `OVHClient::create_record`.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct NewRecord {
+    #[serde(rename = "fieldType")]
+    pub field_type: FieldType,
+    #[serde(rename = "subDomain", default)]
+    pub sub_domain: String,
+    pub target: String,
+    /// `None` for the zone's default TTL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+}
+
+impl NewRecord {
+    pub fn new(field_type: FieldType, sub_domain: &str, target: &str) -> NewRecord {
+        NewRecord {
+            field_type: field_type,
+            sub_domain: sub_domain.to_string(),
+            target: target.to_string(),
+            ttl: None,
+        }
+    }
+}
+
+/// Fields of a record which can be changed, `fieldType` cannot.
+#[derive(Serialize)]
+struct RecordUpdate<'a> {
+    #[serde(rename = "subDomain")]
+    sub_domain: &'a str,
+    target: &'a str,
+    ttl: u32,
+}
+
+/// Filter of `OVHClient::records`, all records when empty.
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct RecordFilter {
+    pub field_type: Option<FieldType>,
+    /// `Some("")` for the records of the zone apex.
+    pub sub_domain: Option<String>,
+}
+
+impl RecordFilter {
+    fn query(&self) -> String {
+        query(&[("fieldType", self.field_type.as_ref().map(|t| t.to_string())),
+                ("subDomain", self.sub_domain.clone())])
+    }
+}
+
+fn zone_path(zone: &str) -> String {
+    format!("/domain/zone/{}", encode(zone))
+}
+
+impl OVHClient {
+    /// Names of the zones of the account.
+    pub fn zones(&self) -> Result<Vec<String>, Error> {
+        decode(self.execute("GET", "/domain/zone", "")?)
+    }
+
+    pub fn zone(&self, zone: &str) -> Result<Zone, Error> {
+        decode(self.execute("GET", &zone_path(zone), "")?)
+    }
+
+    /// Ids of the records of `zone` matching `filter`.
+    pub fn record_ids(&self, zone: &str, filter: &RecordFilter) -> Result<Vec<u64>, Error> {
+        let path = format!("{}/record{}", zone_path(zone), filter.query());
+        decode(self.execute("GET", &path, "")?)
+    }
+
+    /// Records of `zone` matching `filter`, sorted by id.
+    pub fn records(&self, zone: &str, filter: &RecordFilter) -> Result<Vec<Record>, Error> {
+        let path = format!("{}/record{}", zone_path(zone), filter.query());
+        let mut records = self.fetch_all::<Record>(&path, CONCURRENCY)?
+            .collect::<Result<Vec<Record>, Error>>()?;
+        records.sort_by_key(|record| record.id);
+        Ok(records)
+    }
+
+    pub fn record(&self, zone: &str, id: u64) -> Result<Record, Error> {
+        decode(self.execute("GET", &format!("{}/record/{}", zone_path(zone), id), "")?)
+    }
+
+    /// Create a record in `zone`, served once the zone is refreshed.
+    ///
+    /// Returns `None` in dry-run mode, where the record is only planned.
+    pub fn create_record(&self, zone: &str, record: &NewRecord) -> Result<Option<Record>, Error> {
+        let body = serde_json::to_string(record)?;
+        let response = self.execute("POST", &format!("{}/record", zone_path(zone)), &body)?;
+        if response.header("X-Ovh-Dry-Run") == Some("true") {
+            return Ok(None);
+        }
+        decode(response).map(Some)
+    }
+
+    /// Save the sub-domain, target and TTL of `record`, its type cannot
+    /// be changed.
+    pub fn update_record(&self, record: &Record) -> Result<(), Error> {
+        let body = serde_json::to_string(&RecordUpdate {
+                sub_domain: &record.sub_domain,
+                target: &record.target,
+                ttl: record.ttl,
+            })?;
+        let path = format!("{}/record/{}", zone_path(&record.zone), record.id);
+        self.execute("PUT", &path, &body)?.error_for_status()?;
+        Ok(())
+    }
+
+    pub fn delete_record(&self, zone: &str, id: u64) -> Result<(), Error> {
+        let path = format!("{}/record/{}", zone_path(zone), id);
+        self.execute("DELETE", &path, "")?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Apply the pending record changes of `zone`.
+    pub fn refresh_zone(&self, zone: &str) -> Result<(), Error> {
+        self.execute("POST", &format!("{}/refresh", zone_path(zone)), "")?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use super::{FieldType, NewRecord, Record, RecordFilter};
+    use super::serde_json;
+    use client::OVHClient;
+    use config::Credential;
+    use error::Error;
+    use transport::tests::{stubbed, Stub};
+
+    /// A client whose calls are answered with the body of the first
+    /// route ending their url, 404 otherwise.
+    pub fn client(routes: Vec<(&'static str, &'static str, &'static str)>) -> (OVHClient, Arc<Stub>) {
+        let (client, stub) =
+            stubbed(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"));
+        for (method, path, body) in routes {
+            stub.on(method, path, 200, body);
+        }
+        (client, stub)
+    }
+
+    #[test]
+    fn test_field_type() {
+        assert_eq!(FieldType::MX, FieldType::from_api("MX"));
+        assert_eq!("SVCB", FieldType::from_api("SVCB").to_string());
+        assert_eq!("\"AAAA\"", serde_json::to_string(&FieldType::AAAA).unwrap());
+    }
+
+    #[test]
+    fn test_record() {
+        let record: Record = serde_json::from_str(r#"{"id": 42, "zone": "example.com",
+            "fieldType": "A", "subDomain": null, "target": "192.0.2.1", "ttl": null}"#)
+            .unwrap();
+        assert_eq!("", record.sub_domain);
+        assert_eq!(0, record.ttl);
+
+        let mut new = NewRecord::new(FieldType::TXT, "", "\"v=spf1 -all\"");
+        assert_eq!(r#"{"fieldType":"TXT","subDomain":"","target":"\"v=spf1 -all\""}"#,
+                   serde_json::to_string(&new).unwrap());
+        new.ttl = Some(60);
+        assert_eq!(true, serde_json::to_string(&new).unwrap().ends_with(r#","ttl":60}"#));
+    }
+
+    #[test]
+    fn test_records() {
+        let (client, api) = client(vec![
+            ("GET", "/domain/zone/example.com/record?fieldType=A&subDomain=www%20a", "[2, 1]"),
+            ("GET", "/record/1", r#"{"id": 1, "zone": "example.com", "fieldType": "A",
+                                     "subDomain": "www a", "target": "192.0.2.1", "ttl": 0}"#),
+            ("GET", "/record/2", r#"{"id": 2, "zone": "example.com", "fieldType": "A",
+                                     "subDomain": "www a", "target": "192.0.2.2", "ttl": 60}"#),
+        ]);
+        let filter = RecordFilter {
+            field_type: Some(FieldType::A),
+            sub_domain: Some("www a".to_string()),
+        };
+        let records = client.records("example.com", &filter).unwrap();
+        assert_eq!(vec![1, 2], records.iter().map(|r| r.id).collect::<Vec<u64>>());
+        assert_eq!(3, api.sent.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_changes() {
+        let created = r#"{"id": 7, "zone": "example.com", "fieldType": "CNAME",
+                          "subDomain": "www", "target": "example.com.", "ttl": 0}"#;
+        let (client, api) = client(vec![("POST", "/domain/zone/example.com/record", created),
+                                        ("PUT", "/domain/zone/example.com/record/7", ""),
+                                        ("DELETE", "/domain/zone/example.com/record/7", ""),
+                                        ("POST", "/domain/zone/example.com/refresh", "")]);
+        let new = NewRecord::new(FieldType::CNAME, "www", "example.com.");
+        let mut record = client.create_record("example.com", &new).unwrap().unwrap();
+        record.ttl = 300;
+        client.update_record(&record).unwrap();
+        client.delete_record("example.com", 7).unwrap();
+        client.refresh_zone("example.com").unwrap();
+        match client.delete_record("example.com", 8) {
+            Err(Error::Api { status, .. }) => assert_eq!(404, status),
+            other => panic!("expected a 404, got {:?}", other),
+        }
+
+        let sent = api.sent.lock().unwrap();
+        assert_eq!(r#"{"subDomain":"www","target":"example.com.","ttl":300}"#, sent[1].body);
+        assert_eq!(5, sent.len());
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let (mut client, api) = client(vec![]);
+        client.set_dry_run(true);
+        let new = NewRecord::new(FieldType::A, "www", "192.0.2.1");
+        assert_eq!(None, client.create_record("example.com", &new).unwrap());
+        client.refresh_zone("example.com").unwrap();
+        assert_eq!(0, api.sent.lock().unwrap().len());
+        assert_eq!(2, client.planned_calls().len());
+    }
+
+    #[test]
+    fn test_api_errors() {
+        let (client, _) = client(vec![("GET", "/domain/zone/example.com/record", "[1]")]);
+        match client.records("unknown.com", &RecordFilter::default()) {
+            Err(Error::Api { status, .. }) => assert_eq!(404, status),
+            other => panic!("expected a 404, got {:?}", other),
+        }
+        // The item itself is missing
+        match client.records("example.com", &RecordFilter::default()) {
+            Err(Error::Api { status, .. }) => assert_eq!(404, status),
+            other => panic!("expected a 404, got {:?}", other),
+        }
+    }
+}
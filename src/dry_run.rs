@@ -0,0 +1,71 @@
+//! # Dry run
+//!
+//! In dry-run mode (see `OVHClient::set_dry_run`), mutating calls are
+//! signed and go through the middlewares as usual, but are recorded in a
+//! plan instead of being sent. Read-only calls are still sent.
+//!
+use client::{Request, Response};
+
+/// A call that would have been sent.
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct PlannedCall {
+    pub method: String,
+    pub url: String,
+    pub body: String,
+}
+
+impl<'a> From<&'a Request> for PlannedCall {
+    fn from(request: &'a Request) -> PlannedCall {
+        PlannedCall {
+            method: request.method.clone(),
+            url: request.url.clone(),
+            body: request.body.clone(),
+        }
+    }
+}
+
+/// Whether a call with this method changes something on OVH's side.
+pub fn is_mutating(method: &str) -> bool {
+    match method {
+        "GET" | "HEAD" | "OPTIONS" => false,
+        _ => true,
+    }
+}
+
+/// Response given to skipped calls, like the API answers most mutating calls.
+pub fn response() -> Response {
+    Response {
+        status: 200,
+        headers: vec![("X-Ovh-Dry-Run".to_string(), "true".to_string())],
+        body: "null".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_mutating, PlannedCall};
+    use client::Request;
+
+    #[test]
+    fn test_is_mutating() {
+        assert_eq!(false, is_mutating("GET"));
+        assert_eq!(false, is_mutating("HEAD"));
+        assert_eq!(true, is_mutating("POST"));
+        assert_eq!(true, is_mutating("PUT"));
+        assert_eq!(true, is_mutating("DELETE"));
+    }
+
+    #[test]
+    fn test_planned_call_from_request() {
+        let request = Request {
+            method: "POST".to_string(),
+            url: "https://eu.api.ovh.com/1.0/order/cart".to_string(),
+            headers: vec![("X-Ovh-Consumer".to_string(), "ck".to_string())],
+            body: "{}".to_string(),
+        };
+        let call = PlannedCall::from(&request);
+        assert_eq!("POST", call.method);
+        assert_eq!("https://eu.api.ovh.com/1.0/order/cart", call.url);
+        assert_eq!("{}", call.body);
+    }
+}
@@ -0,0 +1,433 @@
+//! # DynHost
+//!
+//! Dynamic DNS records of `/domain/zone/{zoneName}/dynHost`, and their
+//! logins, for hosts whose public IP changes, e.g. branch offices.
+//!
+//! `Updater` keeps a DynHost record pointing to the current public IP
+//! given by an `IpSource`, only calling the API when the IP changes.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use ovh::OVHClient;
+//! use ovh::dynhost::{HttpIpSource, Updater};
+//!
+//! let client = OVHClient::new();
+//! let source = HttpIpSource::new(client.transport());
+//! let mut updater = Updater::new(client, "example.com", "office", source);
+//! updater.run(Duration::from_secs(300));
+//! ```
+//!
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use client::{OVHClient, Request};
+use codegen::{decode, encode, query, Body};
+use dns::null_as_default;
+use error::Error;
+use secret::Secret;
+use transport::Transport;
+
+/// Service answering a GET with the caller's public IP, as plain text.
+pub const DEFAULT_IP_URL: &'static str = "https://api.ipify.org";
+
+/// A DynHost record, as returned by `GET /domain/zone/{zoneName}/dynHost/record/{id}`.
+#[derive(Debug,Clone,PartialEq,Deserialize)]
+pub struct DynHostRecord {
+    pub id: u64,
+    pub zone: String,
+    /// Empty for the zone apex.
+    #[serde(rename = "subDomain", default, deserialize_with = "null_as_default")]
+    pub sub_domain: String,
+    pub ip: IpAddr,
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub ttl: u32,
+}
+
+/// A login allowed to update the DynHost records of some sub-domains
+/// with the DynDNS protocol, e.g. from a router.
+#[derive(Debug,Clone,PartialEq,Deserialize)]
+pub struct DynHostLogin {
+    /// e.g. `example.com-office`
+    pub login: String,
+    pub zone: String,
+    /// Sub-domains it may update, `*` for all.
+    #[serde(rename = "subDomain")]
+    pub sub_domain: String,
+}
+
+fn dynhost_path(zone: &str, kind: &str) -> String {
+    format!("/domain/zone/{}/dynHost/{}", encode(zone), kind)
+}
+
+impl OVHClient {
+    /// Ids of the DynHost records of `zone`, only those of `sub_domain` if given.
+    pub fn dynhost_record_ids(&self,
+                              zone: &str,
+                              sub_domain: Option<&str>)
+                              -> Result<Vec<u64>, Error> {
+        let path = dynhost_path(zone, "record") +
+                   &query(&[("subDomain", sub_domain.map(|s| s.to_string()))]);
+        decode(self.execute("GET", &path, "")?)
+    }
+
+    pub fn dynhost_record(&self, zone: &str, id: u64) -> Result<DynHostRecord, Error> {
+        let path = format!("{}/{}", dynhost_path(zone, "record"), id);
+        decode(self.execute("GET", &path, "")?)
+    }
+
+    /// DynHost records of `zone`, only those of `sub_domain` if given.
+    pub fn dynhost_records(&self,
+                           zone: &str,
+                           sub_domain: Option<&str>)
+                           -> Result<Vec<DynHostRecord>, Error> {
+        self.dynhost_record_ids(zone, sub_domain)?
+            .into_iter()
+            .map(|id| self.dynhost_record(zone, id))
+            .collect()
+    }
+
+    /// Returns `None` in dry-run mode, where the record is only planned.
+    pub fn create_dynhost_record(&self,
+                                 zone: &str,
+                                 sub_domain: &str,
+                                 ip: IpAddr)
+                                 -> Result<Option<DynHostRecord>, Error> {
+        let mut body = Body::new();
+        body.set("subDomain", &sub_domain)?;
+        body.set("ip", &ip)?;
+        let response = self.execute("POST", &dynhost_path(zone, "record"), &body.to_json())?;
+        if response.header("X-Ovh-Dry-Run") == Some("true") {
+            return Ok(None);
+        }
+        decode(response).map(Some)
+    }
+
+    /// Save the sub-domain and IP of `record`.
+    pub fn update_dynhost_record(&self, record: &DynHostRecord) -> Result<(), Error> {
+        let mut body = Body::new();
+        body.set("subDomain", &record.sub_domain)?;
+        body.set("ip", &record.ip)?;
+        let path = format!("{}/{}", dynhost_path(&record.zone, "record"), record.id);
+        self.execute("PUT", &path, &body.to_json())?.error_for_status()?;
+        Ok(())
+    }
+
+    pub fn delete_dynhost_record(&self, zone: &str, id: u64) -> Result<(), Error> {
+        let path = format!("{}/{}", dynhost_path(zone, "record"), id);
+        self.execute("DELETE", &path, "")?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Names of the DynHost logins of `zone`.
+    pub fn dynhost_logins(&self, zone: &str) -> Result<Vec<String>, Error> {
+        decode(self.execute("GET", &dynhost_path(zone, "login"), "")?)
+    }
+
+    pub fn dynhost_login(&self, zone: &str, login: &str) -> Result<DynHostLogin, Error> {
+        let path = format!("{}/{}", dynhost_path(zone, "login"), encode(login));
+        decode(self.execute("GET", &path, "")?)
+    }
+
+    /// Create the login `{zone}-{login_suffix}`, allowed to update the
+    /// DynHost records of `sub_domain` (`*` for all).
+    ///
+    /// Returns `None` in dry-run mode, where the login is only planned.
+    pub fn create_dynhost_login(&self,
+                                zone: &str,
+                                login_suffix: &str,
+                                sub_domain: &str,
+                                password: &Secret)
+                                -> Result<Option<DynHostLogin>, Error> {
+        let mut body = Body::new();
+        body.set("loginSuffix", &login_suffix)?;
+        body.set("subDomain", &sub_domain)?;
+        body.set("password", &password.expose())?;
+        let response = self.execute("POST", &dynhost_path(zone, "login"), &body.to_json())?;
+        if response.header("X-Ovh-Dry-Run") == Some("true") {
+            return Ok(None);
+        }
+        decode(response).map(Some)
+    }
+
+    pub fn change_dynhost_password(&self,
+                                   zone: &str,
+                                   login: &str,
+                                   password: &Secret)
+                                   -> Result<(), Error> {
+        let path = format!("{}/{}/changePassword", dynhost_path(zone, "login"), encode(login));
+        let mut body = Body::new();
+        body.set("password", &password.expose())?;
+        self.execute("POST", &path, &body.to_json())?.error_for_status()?;
+        Ok(())
+    }
+
+    pub fn delete_dynhost_login(&self, zone: &str, login: &str) -> Result<(), Error> {
+        let path = format!("{}/{}", dynhost_path(zone, "login"), encode(login));
+        self.execute("DELETE", &path, "")?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Where `Updater` gets the current public IP from.
+pub trait IpSource: Send + Sync {
+    fn current_ip(&self) -> Result<IpAddr, Error>;
+}
+
+impl<F> IpSource for F
+    where F: Fn() -> Result<IpAddr, Error> + Send + Sync
+{
+    fn current_ip(&self) -> Result<IpAddr, Error> {
+        self()
+    }
+}
+
+/// Public IP as answered in plain text by a web service, `DEFAULT_IP_URL`
+/// unless `url` is changed.
+pub struct HttpIpSource {
+    pub url: String,
+    transport: Arc<dyn Transport>,
+}
+
+impl HttpIpSource {
+    /// A source sending its requests with `transport`, e.g. `OVHClient::transport`.
+    pub fn new(transport: Arc<dyn Transport>) -> HttpIpSource {
+        HttpIpSource {
+            url: DEFAULT_IP_URL.to_string(),
+            transport: transport,
+        }
+    }
+}
+
+impl IpSource for HttpIpSource {
+    fn current_ip(&self) -> Result<IpAddr, Error> {
+        let request = Request {
+            method: "GET".to_string(),
+            url: self.url.clone(),
+            headers: vec![("Accept".to_string(), "text/plain".to_string())],
+            body: String::new(),
+        };
+        let response = self.transport.send(&request)?.error_for_status()?;
+        let body = response.body.trim();
+        body.parse().map_err(|_| Error::Http(format!("{} answered {:?}, not an IP", self.url, body)))
+    }
+}
+
+/// Keeps the DynHost record of a sub-domain pointing to the current public IP.
+pub struct Updater<S> {
+    client: OVHClient,
+    zone: String,
+    sub_domain: String,
+    source: S,
+    /// IP the record was last seen or set with, once the zone is refreshed.
+    last: Option<IpAddr>,
+    /// Whether the record was changed but the zone not refreshed yet.
+    refresh_pending: bool,
+}
+
+impl<S: IpSource> Updater<S> {
+    pub fn new(client: OVHClient, zone: &str, sub_domain: &str, source: S) -> Updater<S> {
+        Updater {
+            client: client,
+            zone: zone.to_string(),
+            sub_domain: sub_domain.to_string(),
+            source: source,
+            last: None,
+            refresh_pending: false,
+        }
+    }
+
+    /// Update the record if the public IP changed since the last check,
+    /// creating it if needed, then refresh the zone.
+    ///
+    /// Returns the new IP once the record was changed and the zone
+    /// refreshed, a failed refresh being retried at the next check. The
+    /// API is not called while the IP stays the same.
+    pub fn check(&mut self) -> Result<Option<IpAddr>, Error> {
+        let ip = self.source.current_ip()?;
+        if self.last == Some(ip) {
+            return Ok(None);
+        }
+
+        let records = self.client.dynhost_records(&self.zone, Some(&self.sub_domain))?;
+        match records.into_iter().next() {
+            Some(ref record) if record.ip == ip => {}
+            Some(mut record) => {
+                info!("DynHost {}.{}: {} -> {}", self.sub_domain, self.zone, record.ip, ip);
+                record.ip = ip;
+                self.client.update_dynhost_record(&record)?;
+                self.refresh_pending = true;
+            }
+            None => {
+                info!("DynHost {}.{}: creating with {}", self.sub_domain, self.zone, ip);
+                self.client.create_dynhost_record(&self.zone, &self.sub_domain, ip)?;
+                self.refresh_pending = true;
+            }
+        }
+        let changed = self.refresh_pending;
+        if changed {
+            self.client.refresh_zone(&self.zone)?;
+            self.refresh_pending = false;
+        }
+        self.last = Some(ip);
+        Ok(if changed { Some(ip) } else { None })
+    }
+
+    /// Check every `interval`, forever. Failed checks are logged and
+    /// retried at the next one.
+    pub fn run(&mut self, interval: Duration) {
+        loop {
+            if let Err(err) = self.check() {
+                warn!("DynHost {}.{}: {}", self.sub_domain, self.zone, err);
+            }
+            thread::sleep(interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::sync::{Arc, Mutex};
+
+    use super::{HttpIpSource, IpSource, Updater};
+    use dns::tests::client;
+    use error::Error;
+    use secret::Secret;
+    use transport::tests::Stub;
+
+    fn ipify(body: &str) -> Arc<Stub> {
+        let stub = Stub::new();
+        stub.on("GET", "", 200, body);
+        stub
+    }
+
+    #[test]
+    fn test_http_source() {
+        let source = HttpIpSource::new(ipify("192.0.2.7\n"));
+        assert_eq!("192.0.2.7".parse::<IpAddr>().unwrap(), source.current_ip().unwrap());
+        let source = HttpIpSource::new(ipify("<html>"));
+        assert_eq!(true, source.current_ip().is_err());
+    }
+
+    #[test]
+    fn test_updater() {
+        let (client, api) = client(vec![
+            ("GET", "/domain/zone/example.com/dynHost/record?subDomain=office", "[3]"),
+            ("GET", "/dynHost/record/3", r#"{"id": 3, "zone": "example.com",
+                                            "subDomain": "office", "ip": "192.0.2.1", "ttl": 60}"#),
+            ("PUT", "/domain/zone/example.com/dynHost/record/3", ""),
+            ("POST", "/domain/zone/example.com/refresh", ""),
+        ]);
+        let ip = Arc::new(Mutex::new("192.0.2.1"));
+        let current = ip.clone();
+        let source = move || -> Result<IpAddr, Error> { Ok(current.lock().unwrap().parse().unwrap()) };
+        let mut updater = Updater::new(client, "example.com", "office", source);
+
+        // Already up to date, then not checked again while the IP is the same
+        assert_eq!(None, updater.check().unwrap());
+        assert_eq!(None, updater.check().unwrap());
+        assert_eq!(2, api.sent.lock().unwrap().len());
+
+        *ip.lock().unwrap() = "192.0.2.2";
+        assert_eq!(Some("192.0.2.2".parse().unwrap()), updater.check().unwrap());
+        let sent = api.sent.lock().unwrap();
+        assert_eq!(r#"{"ip":"192.0.2.2","subDomain":"office"}"#, sent[4].body);
+        assert_eq!(true, sent[5].url.ends_with("/domain/zone/example.com/refresh"));
+        assert_eq!(6, sent.len());
+    }
+
+    #[test]
+    fn test_updater_create() {
+        let (client, api) = client(vec![
+            ("GET", "/domain/zone/example.com/dynHost/record?subDomain=office", "[]"),
+            ("POST", "/domain/zone/example.com/dynHost/record", r#"{"id": 3,
+                "zone": "example.com", "subDomain": "office", "ip": "192.0.2.1", "ttl": 60}"#),
+            ("POST", "/domain/zone/example.com/refresh", ""),
+        ]);
+        let ip = Arc::new(Mutex::new(None));
+        let current = ip.clone();
+        let source = move || -> Result<IpAddr, Error> {
+            match *current.lock().unwrap() {
+                Some(ip) => Ok(ip),
+                None => Err(Error::Http("no route to host".to_string())),
+            }
+        };
+        let mut updater = Updater::new(client, "example.com", "office", source);
+
+        // The IP is unknown, nothing to do
+        assert_eq!(true, updater.check().is_err());
+        assert_eq!(0, api.sent.lock().unwrap().len());
+
+        // No record yet
+        *ip.lock().unwrap() = Some("192.0.2.1".parse().unwrap());
+        assert_eq!(Some("192.0.2.1".parse().unwrap()), updater.check().unwrap());
+        {
+            let sent = api.sent.lock().unwrap();
+            assert_eq!("POST", sent[1].method);
+            assert_eq!(r#"{"ip":"192.0.2.1","subDomain":"office"}"#, sent[1].body);
+            assert_eq!(true, sent[2].url.ends_with("/domain/zone/example.com/refresh"));
+            assert_eq!(3, sent.len());
+        }
+
+        // A failing source does not forget the IP the record was set with
+        *ip.lock().unwrap() = None;
+        assert_eq!(true, updater.check().is_err());
+        *ip.lock().unwrap() = Some("192.0.2.1".parse().unwrap());
+        assert_eq!(None, updater.check().unwrap());
+        assert_eq!(3, api.sent.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_updater_retries_refresh() {
+        let (client, api) = client(vec![
+            ("GET", "/domain/zone/example.com/dynHost/record?subDomain=office", "[3]"),
+            ("GET", "/dynHost/record/3", r#"{"id": 3, "zone": "example.com",
+                                            "subDomain": "office", "ip": "192.0.2.1", "ttl": 60}"#),
+            // Saved by the first check
+            ("GET", "/dynHost/record/3", r#"{"id": 3, "zone": "example.com",
+                                            "subDomain": "office", "ip": "192.0.2.2", "ttl": 60}"#),
+            ("PUT", "/domain/zone/example.com/dynHost/record/3", ""),
+        ]);
+        api.on("POST", "/domain/zone/example.com/refresh", 500, r#"{"message": "Internal error"}"#);
+        api.on("POST", "/domain/zone/example.com/refresh", 200, "");
+        let source = || -> Result<IpAddr, Error> { Ok("192.0.2.2".parse().unwrap()) };
+        let mut updater = Updater::new(client, "example.com", "office", source);
+
+        match updater.check() {
+            Err(Error::Api { status, .. }) => assert_eq!(500, status),
+            other => panic!("expected a failed refresh, got {:?}", other),
+        }
+        // The record is up to date but the zone still has to be refreshed
+        assert_eq!(Some("192.0.2.2".parse().unwrap()), updater.check().unwrap());
+        assert_eq!(None, updater.check().unwrap());
+
+        let sent = api.sent.lock().unwrap();
+        let calls: Vec<&str> = sent.iter().map(|request| request.method.as_str()).collect();
+        assert_eq!(vec!["GET", "GET", "PUT", "POST", "GET", "GET", "POST"], calls);
+    }
+
+    #[test]
+    fn test_updater_dry_run() {
+        let (mut client, api) = client(vec![
+            ("GET", "/domain/zone/example.com/dynHost/record?subDomain=office", "[]"),
+        ]);
+        client.set_dry_run(true);
+        let source = || -> Result<IpAddr, Error> { Ok("192.0.2.1".parse().unwrap()) };
+        let mut updater = Updater::new(client.clone(), "example.com", "office", source);
+
+        assert_eq!(Some("192.0.2.1".parse().unwrap()), updater.check().unwrap());
+        assert_eq!(1, api.sent.lock().unwrap().len());
+        let plan = client.planned_calls();
+        assert_eq!(r#"{"ip":"192.0.2.1","subDomain":"office"}"#, plan[0].body);
+        assert_eq!(true, plan[1].url.ends_with("/domain/zone/example.com/refresh"));
+        assert_eq!(2, plan.len());
+
+        let login = client.create_dynhost_login("example.com",
+                                                "office",
+                                                "office",
+                                                &Secret::new("hunter22"));
+        assert_eq!(None, login.unwrap());
+    }
+}
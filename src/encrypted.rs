@@ -0,0 +1,171 @@
+//! # Encrypted
+//!
+//! Encrypt configuration files at rest with AES-256-GCM,
+//! using a key derived from a passphrase (or the content of a key file)
+//! with PBKDF2-HMAC-SHA256.
+//!
+//! Layout of an encrypted file:
+//! `OVHRS1` magic, 16 bytes salt, 12 bytes nonce, 16 bytes tag, ciphertext.
+//!
+//! This is not the format of the `Config.toml.enc` files used by CI,
+//! which are decrypted by openssl/secure-file before the build.
+//!
+use std::fs::File;
+use std::io::{self, Error, ErrorKind, Read};
+use std::path::Path;
+
+use crypto::aead::{AeadDecryptor, AeadEncryptor};
+use crypto::aes::KeySize;
+use crypto::aes_gcm::AesGcm;
+use crypto::hmac::Hmac;
+use crypto::pbkdf2::pbkdf2;
+use crypto::sha2::Sha256;
+use rand::{OsRng, Rng};
+use zeroize::Zeroize;
+
+use config::write_private;
+
+const MAGIC: &'static [u8] = b"OVHRS1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const ITERATIONS: u32 = 100_000;
+
+/// Derive the AES key from a passphrase.
+fn derive_key(passphrase: &[u8], salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    let mut mac = Hmac::new(Sha256::new(), passphrase);
+    pbkdf2(&mut mac, salt, ITERATIONS, &mut key);
+    key
+}
+
+/// Encrypt `plaintext` with a key derived from `passphrase`.
+pub fn encrypt(plaintext: &[u8], passphrase: &[u8]) -> io::Result<Vec<u8>> {
+    let mut rng = OsRng::new()?;
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut nonce);
+
+    let mut key = derive_key(passphrase, &salt);
+    let mut cipher = AesGcm::new(KeySize::KeySize256, &key, &nonce, MAGIC);
+    key.zeroize();
+
+    let mut ciphertext = vec![0u8; plaintext.len()];
+    let mut tag = [0u8; TAG_LEN];
+    cipher.encrypt(plaintext, &mut ciphertext, &mut tag);
+
+    let mut data = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + TAG_LEN +
+                                      ciphertext.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&tag);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// Decrypt data produced by `encrypt`.
+///
+/// Fails with `InvalidData` on a wrong passphrase or altered content.
+pub fn decrypt(data: &[u8], passphrase: &[u8]) -> io::Result<Vec<u8>> {
+    let header = MAGIC.len() + SALT_LEN + NONCE_LEN + TAG_LEN;
+    if data.len() < header || &data[..MAGIC.len()] != MAGIC {
+        return Err(Error::new(ErrorKind::InvalidData, "Not an encrypted config file"));
+    }
+    let (salt, rest) = data[MAGIC.len()..].split_at(SALT_LEN);
+    let (nonce, rest) = rest.split_at(NONCE_LEN);
+    let (tag, ciphertext) = rest.split_at(TAG_LEN);
+
+    let mut key = derive_key(passphrase, salt);
+    let mut cipher = AesGcm::new(KeySize::KeySize256, &key, nonce, MAGIC);
+    key.zeroize();
+
+    let mut plaintext = vec![0u8; ciphertext.len()];
+    if !cipher.decrypt(ciphertext, &mut plaintext, tag) {
+        plaintext.zeroize();
+        return Err(Error::new(ErrorKind::InvalidData, "Cannot decrypt config file"));
+    }
+    Ok(plaintext)
+}
+
+/// Read and decrypt a file written by `write_encrypted_file`.
+pub fn read_encrypted_file<P: AsRef<Path>>(path: P, passphrase: &[u8]) -> io::Result<String> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    let plaintext = decrypt(&data, passphrase)?;
+    String::from_utf8(plaintext).map_err(|e| {
+        let mut bytes = e.into_bytes();
+        bytes.zeroize();
+        Error::new(ErrorKind::InvalidData, "Decrypted config is not UTF-8")
+    })
+}
+
+/// Encrypt `content` (e.g. a `Config.toml`) into the file at `path`.
+///
+/// On unix the file is only readable by its owner, even if it existed before.
+pub fn write_encrypted_file<P: AsRef<Path>>(path: P,
+                                            content: &str,
+                                            passphrase: &[u8])
+                                            -> io::Result<()> {
+    let data = encrypt(content.as_bytes(), passphrase)?;
+    write_private(path.as_ref(), &data)
+}
+
+/// Read a key file to be used as passphrase, ignoring a trailing newline.
+pub fn read_key_file<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let mut key = Vec::new();
+    File::open(path)?.read_to_end(&mut key)?;
+    while key.last() == Some(&b'\n') || key.last() == Some(&b'\r') {
+        key.pop();
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    use super::{decrypt, encrypt, read_encrypted_file, write_encrypted_file};
+
+    #[test]
+    fn test_roundtrip() {
+        let data = encrypt(b"consumer_key = \"ck\"", b"passphrase").unwrap();
+        let plaintext = decrypt(&data, b"passphrase").unwrap();
+        assert_eq!(b"consumer_key = \"ck\"".to_vec(), plaintext);
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let data = encrypt(b"consumer_key = \"ck\"", b"passphrase").unwrap();
+        assert_eq!(true, decrypt(&data, b"wrong").is_err());
+    }
+
+    #[test]
+    fn test_tampered() {
+        let mut data = encrypt(b"consumer_key = \"ck\"", b"passphrase").unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert_eq!(true, decrypt(&data, b"passphrase").is_err());
+        assert_eq!(true, decrypt(b"garbage", b"passphrase").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_overwrite_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = env::temp_dir().join("ovh-rs-test-overwrite.toml.enc");
+        File::create(&path).unwrap().write_all(b"public").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_encrypted_file(&path, "consumer_key = \"ck\"", b"passphrase").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        let content = read_encrypted_file(&path, b"passphrase").unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(0o600, mode & 0o777);
+        assert_eq!("consumer_key = \"ck\"", content);
+    }
+}
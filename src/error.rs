@@ -0,0 +1,90 @@
+//! # Error
+//!
+//! Errors returned by the client helpers.
+//!
+extern crate serde_json;
+
+use std::error;
+use std::fmt;
+use std::time::Duration;
+
+use bind::ParseError;
+use task::TaskStatus;
+
+/// Errors that can occur while talking to OVH's APIs.
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or its response could not be read.
+    Http(String),
+    /// A response body could not be deserialized into the expected type.
+    Json(serde_json::Error),
+    /// The API answered with an error status, and its message if any.
+    Api {
+        status: u16,
+        message: String,
+    },
+    /// The consumer key validation flow did not complete.
+    Auth(String),
+    /// A call is invalid, e.g. does not match the API schema, it was not sent.
+    Validation(String),
+    /// A zone file is invalid, it was not uploaded.
+    ZoneFile(ParseError),
+    /// A DNS reconciliation cannot be planned or was refused, e.g. to
+    /// change an NS record.
+    Reconcile(String),
+    /// A polled task ended in `error` or `cancelled` status.
+    TaskFailed {
+        status: TaskStatus,
+        comment: String,
+    },
+    /// A polled task did not complete in time.
+    TaskTimeout(Duration),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Http(ref msg) => write!(f, "HTTP error: {}", msg),
+            Error::Json(ref err) => write!(f, "Cannot parse response: {}", err),
+            Error::Api { status, ref message } => write!(f, "API error {}: {}", status, message),
+            Error::Auth(ref msg) => write!(f, "Authentication failed: {}", msg),
+            Error::Validation(ref msg) => write!(f, "Invalid call: {}", msg),
+            Error::ZoneFile(ref err) => write!(f, "Invalid zone file: {}", err),
+            Error::Reconcile(ref msg) => write!(f, "Cannot reconcile zone: {}", msg),
+            Error::TaskFailed { ref status, ref comment } => {
+                write!(f, "Task ended with status {}: {}", status, comment)
+            }
+            Error::TaskTimeout(ref timeout) => write!(f, "Task not done after {:?}", timeout),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Http(ref msg) => msg,
+            Error::Json(ref err) => err.description(),
+            Error::Api { ref message, .. } => message,
+            Error::Auth(ref msg) => msg,
+            Error::Validation(ref msg) => msg,
+            Error::ZoneFile(ref err) => &err.message,
+            Error::Reconcile(ref msg) => msg,
+            Error::TaskFailed { .. } => "task failed",
+            Error::TaskTimeout(_) => "task timed out",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            Error::Json(ref err) => Some(err),
+            Error::ZoneFile(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
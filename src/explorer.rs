@@ -0,0 +1,214 @@
+//! # Explorer
+//!
+//! Discover endpoints without the web console: list the API families of
+//! the `/1.0/` root, search operations of their schemas by keyword, and
+//! describe their parameters and return type.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//!
+//! let client = OVHClient::new();
+//! for family in client.list_apis().unwrap() {
+//!     println!("{}", family.path);
+//! }
+//! let schema = client.fetch_schema("domain").unwrap();
+//! for endpoint in schema.search("dns record") {
+//!     println!("{}", endpoint.summary());
+//! }
+//! ```
+//!
+extern crate serde_json;
+
+use client::OVHClient;
+use error::Error;
+use schema::{Api, Lookup, Operation, ParamType, Parameter, Schema};
+
+/// An API family listed by the `/1.0/` root, e.g. `/domain`.
+#[derive(Debug,Clone,PartialEq,Deserialize)]
+pub struct ApiFamily {
+    pub path: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Root {
+    apis: Vec<ApiFamily>,
+}
+
+/// Family `path` belongs to, the most specific one if several match,
+/// e.g. `/dedicated/server` rather than `/dedicated` for `/dedicated/server/ns1`.
+pub fn family_of<'a>(families: &'a [ApiFamily], path: &str) -> Option<&'a ApiFamily> {
+    let path = path.split('?').next().unwrap_or("");
+    families.iter()
+        .filter(|family| path == family.path || path.starts_with(&format!("{}/", family.path)))
+        .max_by_key(|family| family.path.len())
+}
+
+fn place(param_type: ParamType) -> &'static str {
+    match param_type {
+        ParamType::Path => "path",
+        ParamType::Query => "query",
+        ParamType::Body => "body",
+        ParamType::Header => "header",
+    }
+}
+
+/// An operation and the path template it applies to.
+#[derive(Debug,Clone)]
+pub struct Endpoint<'a> {
+    pub api: &'a Api,
+    pub operation: &'a Operation,
+}
+
+impl<'a> From<Lookup<'a>> for Endpoint<'a> {
+    fn from(found: Lookup<'a>) -> Endpoint<'a> {
+        Endpoint {
+            api: found.api,
+            operation: found.operation,
+        }
+    }
+}
+
+impl<'a> Endpoint<'a> {
+    /// Parameters without which the call fails.
+    pub fn required(&self) -> Vec<&'a Parameter> {
+        self.operation.parameters.iter().filter(|param| param.required).collect()
+    }
+
+    /// Whether the operation is deprecated or about to be deleted.
+    pub fn is_deprecated(&self) -> bool {
+        match self.operation.api_status {
+            Some(ref status) => status.value.starts_with("DEPRECATED") || status.value == "DELETED",
+            None => false,
+        }
+    }
+
+    /// One line description, e.g. `GET /domain/zone - List available services`.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} {}", self.operation.http_method, self.api.path);
+        if let Some(ref description) = self.operation.description {
+            summary.push_str(" - ");
+            summary.push_str(description);
+        }
+        if self.is_deprecated() {
+            summary.push_str(" (deprecated)");
+        }
+        summary
+    }
+
+    /// Summary, then one line per parameter and the return type.
+    pub fn describe(&self) -> String {
+        let mut text = self.summary();
+        let params: Vec<&Parameter> = self.operation
+            .parameters
+            .iter()
+            .filter(|param| param.param_type != ParamType::Header)
+            .collect();
+        if !params.is_empty() {
+            text.push_str("\n\nParameters:");
+        }
+        for param in params {
+            let name = param.name.as_ref().map(|name| name.as_str()).unwrap_or("(body)");
+            let data_type = param.full_type.as_ref().unwrap_or(&param.data_type);
+            text.push_str(&format!("\n    {:<20} {:<6} {:<40} {}",
+                                   name,
+                                   place(param.param_type),
+                                   data_type,
+                                   if param.required { "required" } else { "optional" }));
+            if let Some(ref description) = param.description {
+                text.push_str(&format!("\n        {}", description));
+            }
+        }
+        text.push_str(&format!("\n\nReturns: {}", self.operation.response_type));
+        text
+    }
+}
+
+impl Schema {
+    /// Every operation of the schema, in order.
+    pub fn endpoints(&self) -> Vec<Endpoint> {
+        self.apis
+            .iter()
+            .flat_map(|api| {
+                api.operations.iter().map(move |operation| {
+                    Endpoint {
+                        api: api,
+                        operation: operation,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Operations whose method, path or description contain all the words
+    /// of `keywords`, ignoring case, e.g. `"post record"`.
+    pub fn search(&self, keywords: &str) -> Vec<Endpoint> {
+        let words: Vec<String> = keywords.split_whitespace().map(|w| w.to_lowercase()).collect();
+        self.endpoints()
+            .into_iter()
+            .filter(|endpoint| {
+                let operation = endpoint.operation;
+                let description = operation.description.as_ref().map(|d| d.as_str()).unwrap_or("");
+                let text = format!("{} {} {}", operation.http_method, endpoint.api.path, description)
+                    .to_lowercase();
+                words.iter().all(|word| text.contains(word.as_str()))
+            })
+            .collect()
+    }
+}
+
+impl OVHClient {
+    /// API families of the client's endpoint, e.g. `/domain` or `/me`,
+    /// whose schemas can be downloaded with `fetch_schema`.
+    pub fn list_apis(&self) -> Result<Vec<ApiFamily>, Error> {
+        let response = self.execute_unauthenticated("GET", "/", "")?.error_for_status()?;
+        let root: Root = serde_json::from_str(&response.body)?;
+        Ok(root.apis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{family_of, ApiFamily, Endpoint};
+    use schema::Schema;
+    use schema::tests::DOMAIN;
+
+    fn family(path: &str) -> ApiFamily {
+        ApiFamily {
+            path: path.to_string(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_family_of() {
+        let families = vec![family("/dedicated"), family("/dedicated/server"), family("/domain")];
+        assert_eq!(Some(&families[1]), family_of(&families, "/dedicated/server/ns1?x=1"));
+        assert_eq!(Some(&families[2]), family_of(&families, "/domain"));
+        assert_eq!(None, family_of(&families, "/domains"));
+    }
+
+    #[test]
+    fn test_search() {
+        let schema = Schema::parse(DOMAIN).unwrap();
+        assert_eq!(5, schema.endpoints().len());
+        let found = schema.search("post RECORD");
+        assert_eq!(1, found.len());
+        assert_eq!("POST /domain/zone/{zoneName}/record - Create a new DNS record",
+                   found[0].summary());
+        assert_eq!(3, found[0].required().len());
+        assert_eq!(0, schema.search("record nothing").len());
+    }
+
+    #[test]
+    fn test_describe() {
+        let schema = Schema::parse(DOMAIN).unwrap();
+        let found = schema.operation("GET", "/domain/zone/{zoneName}/record").unwrap();
+        let text = Endpoint::from(found).describe();
+        assert_eq!(true, text.starts_with("GET /domain/zone/{zoneName}/record - Records of the zone"));
+        assert_eq!(true, text.contains("zoneName             path   string"));
+        assert_eq!(true, text.contains("required\n        The zone"));
+        assert_eq!(true, text.ends_with("Returns: long[]"));
+    }
+}
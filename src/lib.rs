@@ -9,8 +9,11 @@
 //!
 extern crate chrono;
 extern crate crypto;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 
-#[macro_use] extern crate hyper;
+extern crate hyper;
 #[cfg(feature= "reqwest")]
 #[macro_use] extern crate reqwest;
 
@@ -20,8 +23,44 @@ extern crate log;
 #[cfg(feature= "curl")]
 extern crate curl;
 
+#[cfg(feature= "tracing")]
+extern crate tracing;
+
+extern crate rand;
+extern crate zeroize;
+
 pub use config::Credential;
 pub use client::OVHClient;
+pub use error::Error;
+pub use secret::Secret;
 
+pub mod auth;
+pub mod bind;
+pub mod cassette;
 pub mod client;
+pub mod clock;
+pub mod codegen;
+pub mod collection;
 pub mod config;
+pub mod dns;
+pub mod dry_run;
+pub mod dynhost;
+pub mod encrypted;
+pub mod error;
+pub mod explorer;
+pub mod middleware;
+pub mod proxy;
+pub mod reconcile;
+pub mod reload;
+pub mod schema;
+pub mod secret;
+mod server;
+pub mod signature;
+pub mod task;
+pub mod tenant;
+#[cfg(feature= "testing")]
+pub mod testing;
+#[cfg(feature= "tracing")]
+mod trace;
+pub mod transport;
+pub mod validate;
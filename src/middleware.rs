@@ -0,0 +1,193 @@
+//! # Middleware
+//!
+//! Hooks run by `OVHClient::execute` around every call, once the
+//! request is signed and until its response is read.
+//!
+//! Any `Fn(&mut Request, Next) -> Result<Response, Error>` closure is a
+//! middleware, e.g. to add a correlation header:
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//!
+//! let mut client = OVHClient::new();
+//! client.add_middleware(|request: &mut ovh::client::Request, next: ovh::middleware::Next| {
+//!     request.set_header("X-Correlation-Id", "42");
+//!     next.run(request)
+//! });
+//! ```
+//!
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cassette::scrub_body;
+use client::{Request, Response};
+use error::Error;
+
+/// A layer of the chain.
+///
+/// Implementations inspect or modify the request, hand it to the rest of
+/// the chain with `next.run(request)`, then inspect or modify the response.
+/// Changing the method, url or body invalidates the signature.
+pub trait Middleware: Send + Sync {
+    fn handle(&self, request: &mut Request, next: Next) -> Result<Response, Error>;
+}
+
+impl<F> Middleware for F
+    where F: Fn(&mut Request, Next) -> Result<Response, Error> + Send + Sync
+{
+    fn handle(&self, request: &mut Request, next: Next) -> Result<Response, Error> {
+        self(request, next)
+    }
+}
+
+/// The remaining middlewares of the chain, then the actual sending.
+pub struct Next<'a> {
+    middlewares: &'a [Arc<dyn Middleware>],
+    send: &'a dyn Fn(&Request) -> Result<Response, Error>,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(middlewares: &'a [Arc<dyn Middleware>],
+                      send: &'a dyn Fn(&Request) -> Result<Response, Error>)
+                      -> Next<'a> {
+        Next {
+            middlewares: middlewares,
+            send: send,
+        }
+    }
+
+    /// Run the rest of the chain.
+    pub fn run(self, request: &mut Request) -> Result<Response, Error> {
+        match self.middlewares.split_first() {
+            Some((first, rest)) => first.handle(request, Next::new(rest, self.send)),
+            None => (self.send)(request),
+        }
+    }
+}
+
+/// Log every call at info level, and bodies at debug level when `bodies` is set.
+///
+/// Consumer keys and passwords are scrubbed from logged bodies, as in cassettes.
+#[derive(Debug,Clone,Default)]
+pub struct Logger {
+    pub bodies: bool,
+}
+
+impl Middleware for Logger {
+    fn handle(&self, request: &mut Request, next: Next) -> Result<Response, Error> {
+        if self.bodies && !request.body.is_empty() {
+            debug!("{} {} body: {}", request.method, request.url, scrub_body(&request.body));
+        }
+        let response = next.run(request);
+        match response {
+            Ok(ref response) => {
+                info!("{} {} -> {}", request.method, request.url, response.status);
+                if self.bodies {
+                    debug!("{} {} response: {}",
+                           request.method,
+                           request.url,
+                           scrub_body(&response.body));
+                }
+            }
+            Err(ref err) => info!("{} {} failed: {}", request.method, request.url, err),
+        }
+        response
+    }
+}
+
+/// Measure every call and hand the status (if any) and the elapsed
+/// time to a callback, e.g. to feed metrics.
+pub struct Timer<F> {
+    callback: F,
+}
+
+impl<F> Timer<F>
+    where F: Fn(&Request, Option<u16>, Duration) + Send + Sync
+{
+    pub fn new(callback: F) -> Timer<F> {
+        Timer { callback: callback }
+    }
+}
+
+impl<F> Middleware for Timer<F>
+    where F: Fn(&Request, Option<u16>, Duration) + Send + Sync
+{
+    fn handle(&self, request: &mut Request, next: Next) -> Result<Response, Error> {
+        let start = Instant::now();
+        let response = next.run(request);
+        let status = response.as_ref().ok().map(|r| r.status);
+        (self.callback)(request, status, start.elapsed());
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{Middleware, Next, Timer};
+    use client::{Request, Response};
+    use error::Error;
+
+    fn request() -> Request {
+        Request {
+            method: "GET".to_string(),
+            url: "https://eu.api.ovh.com/1.0/me".to_string(),
+            headers: vec![],
+            body: "".to_string(),
+        }
+    }
+
+    fn echo(request: &Request) -> Result<Response, Error> {
+        Ok(Response {
+            status: 200,
+            headers: request.headers.clone(),
+            body: "{}".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_chain_order() {
+        let first = |request: &mut Request, next: Next| {
+            request.set_header("X-Trace", "first");
+            next.run(request)
+        };
+        let second = |request: &mut Request, next: Next| {
+            let trace = request.header("X-Trace").unwrap_or("").to_string() + ",second";
+            request.set_header("X-Trace", &trace);
+            next.run(request)
+        };
+        let middlewares: Vec<Arc<dyn Middleware>> = vec![Arc::new(first), Arc::new(second)];
+
+        let mut req = request();
+        let response = Next::new(&middlewares, &echo).run(&mut req).unwrap();
+        assert_eq!(Some("first,second"), response.header("x-trace"));
+    }
+
+    #[test]
+    fn test_modify_response() {
+        let status = |request: &mut Request, next: Next| {
+            next.run(request).map(|mut response| {
+                response.status = 204;
+                response
+            })
+        };
+        let middlewares: Vec<Arc<dyn Middleware>> = vec![Arc::new(status)];
+
+        let response = Next::new(&middlewares, &echo).run(&mut request()).unwrap();
+        assert_eq!(204, response.status);
+    }
+
+    #[test]
+    fn test_timer() {
+        let seen = Arc::new(Mutex::new(None));
+        let timer_seen = seen.clone();
+        let timer = Timer::new(move |_: &Request, status: Option<u16>, _| {
+            *timer_seen.lock().unwrap() = status;
+        });
+        let middlewares: Vec<Arc<dyn Middleware>> = vec![Arc::new(timer)];
+
+        Next::new(&middlewares, &echo).run(&mut request()).unwrap();
+        assert_eq!(Some(200), *seen.lock().unwrap());
+    }
+}
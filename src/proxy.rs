@@ -0,0 +1,291 @@
+//! # Proxy
+//!
+//! Local HTTP proxy signing calls on behalf of tools which have no OVH
+//! SDK: an unsigned `GET /1.0/me` sent to the proxy is signed with the
+//! proxy's `Credential` and forwarded to the OVH endpoint.
+//!
+//! Only calls matching an `AccessRule` of the allowlist are forwarded,
+//! others get a 403. Responses are relayed as they are received, so that
+//! large ones are not held in memory.
+//!
+//! At most `workers` connections are served at once, and a client has
+//! `timeout` to send its request and to read each part of the response.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//! use ovh::auth::AccessRule;
+//! use ovh::proxy::Proxy;
+//!
+//! let proxy = Proxy::new(OVHClient::new(), vec![AccessRule::new("GET", "/domain/*")]);
+//! proxy.serve("127.0.0.1:8017").unwrap();
+//! ```
+//!
+use std::cmp;
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::sync_channel;
+use std::thread;
+use std::time::Duration;
+
+use auth::AccessRule;
+use client::{OVHClient, Request, Response};
+use error::Error;
+use server::{error_response, read_request, reject, write_head};
+use transport::{self, ResponseSink};
+use validate::decode;
+
+/// Connections a proxy serves at once by default.
+pub const DEFAULT_WORKERS: usize = 16;
+
+pub struct Proxy {
+    client: OVHClient,
+    allow: Vec<AccessRule>,
+    /// Connections served at once, the next ones wait to be accepted.
+    pub workers: usize,
+    /// Longest wait for a client to send its request or read its response.
+    pub timeout: Duration,
+}
+
+/// Whether `path` has `.` or `..` segments once decoded, or is left with
+/// escaped dots or slashes, e.g. double-encoded ones.
+fn has_dot_segments(path: &str) -> bool {
+    let decoded = decode(path.split('?').next().unwrap_or(""));
+    let escaped = decoded.to_lowercase();
+    decoded.split('/').any(|segment| segment == "." || segment == "..") ||
+    escaped.contains("%2e") || escaped.contains("%2f")
+}
+
+/// Sink relaying a response to the client of the proxy, as it is received.
+struct Connection<'a>(&'a TcpStream);
+
+impl<'a> ResponseSink for Connection<'a> {
+    fn head(&mut self, status: u16, headers: &[(String, String)]) -> io::Result<()> {
+        write_head(self.0, status, headers, None)
+    }
+
+    fn chunk(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let mut stream = self.0;
+        stream.write_all(chunk)
+    }
+}
+
+/// Remembers whether a response was started, so that it is not followed
+/// by an error response.
+struct Tracked<'a> {
+    sink: &'a mut dyn ResponseSink,
+    started: bool,
+}
+
+impl<'a> ResponseSink for Tracked<'a> {
+    fn head(&mut self, status: u16, headers: &[(String, String)]) -> io::Result<()> {
+        self.started = true;
+        self.sink.head(status, headers)
+    }
+
+    fn chunk(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.sink.chunk(chunk)
+    }
+}
+
+impl Proxy {
+    /// A proxy signing calls with `client`, only forwarding those allowed by `allow`.
+    pub fn new(client: OVHClient, allow: Vec<AccessRule>) -> Proxy {
+        Proxy {
+            client: client,
+            allow: allow,
+            workers: DEFAULT_WORKERS,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Path of the API call a request received by the proxy is for, or the
+    /// response to give if it is not forwarded.
+    fn route<'a>(&self, request: &'a Request) -> Result<&'a str, Response> {
+        if !request.url.starts_with("/1.0/") {
+            return Err(error_response(404, "Only /1.0/ calls are proxied"));
+        }
+        let path = &request.url["/1.0".len()..];
+        if has_dot_segments(path) ||
+           !self.allow.iter().any(|rule| rule.matches(&request.method, path)) {
+            info!("Proxy denied {} {}", request.method, path);
+            return Err(error_response(403, &format!("{} {} is not allowed by the proxy",
+                                                    request.method,
+                                                    path)));
+        }
+        Ok(path)
+    }
+
+    /// Answer a request received by the proxy, whose `url` is the request
+    /// target, e.g. `/1.0/domain?whois=true`.
+    ///
+    /// The response of a forwarded call is handed to `sink` as it is
+    /// received from OVH.
+    pub fn handle(&self, request: &Request, sink: &mut dyn ResponseSink) -> Result<(), Error> {
+        let path = match self.route(request) {
+            Ok(path) => path,
+            Err(response) => return transport::deliver(&response, sink),
+        };
+
+        let mut tracked = Tracked {
+            sink: sink,
+            started: false,
+        };
+        match self.client.execute_streaming(&request.method, path, &request.body, &mut tracked) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                warn!("Proxy cannot forward {} {}: {}", request.method, path, err);
+                if tracked.started {
+                    // Too late for an error response, the client sees a truncated body
+                    return Err(err);
+                }
+                transport::deliver(&error_response(502, &err.to_string()), tracked.sink)
+            }
+        }
+    }
+
+    /// Read a request from `stream` and answer it.
+    fn serve_connection(&self, stream: &TcpStream) {
+        let timeouts = stream.set_read_timeout(Some(self.timeout))
+            .and_then(|_| stream.set_write_timeout(Some(self.timeout)));
+        if let Err(err) = timeouts {
+            warn!("Proxy cannot set connection timeouts: {}", err);
+            return;
+        }
+        let mut connection = Connection(stream);
+        let answered = match read_request(stream) {
+            Ok(request) => self.handle(&request, &mut connection),
+            Err(err) => transport::deliver(&reject(&err), &mut connection),
+        };
+        if let Err(err) = answered {
+            debug!("Proxy cannot write response: {}", err);
+        }
+    }
+
+    /// Listen on `addr` and serve connections with `workers` threads.
+    ///
+    /// Only bind to a loopback address: anyone reaching the proxy can
+    /// make the allowed calls with its `Credential`.
+    pub fn serve<A: ToSocketAddrs>(self, addr: A) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        info!("Proxy listening on {}", listener.local_addr()?);
+        let proxy = Arc::new(self);
+
+        // Connections are handed to idle workers only, the others wait
+        // in the listen backlog
+        let (sender, receiver) = sync_channel::<TcpStream>(0);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..cmp::max(1, proxy.workers) {
+            let proxy = proxy.clone();
+            let receiver = receiver.clone();
+            thread::spawn(move || loop {
+                let stream = match receiver.lock().unwrap().recv() {
+                    Ok(stream) => stream,
+                    Err(_) => break,
+                };
+                proxy.serve_connection(&stream);
+            });
+        }
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    warn!("Proxy cannot accept connection: {}", err);
+                    continue;
+                }
+            };
+            if sender.send(stream).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::Arc;
+
+    use super::Proxy;
+    use auth::AccessRule;
+    use client::Request;
+    use config::Credential;
+    use transport::{Buffer, ResponseSink};
+    use transport::tests::{stubbed, Stub};
+
+    /// Records the chunks it is given.
+    #[derive(Default)]
+    struct Chunks(Vec<Vec<u8>>);
+
+    impl ResponseSink for Chunks {
+        fn head(&mut self, _: u16, _: &[(String, String)]) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn chunk(&mut self, chunk: &[u8]) -> io::Result<()> {
+            self.0.push(chunk.to_vec());
+            Ok(())
+        }
+    }
+
+    fn proxy() -> (Proxy, Arc<Stub>) {
+        let (client, upstream) =
+            stubbed(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"));
+        upstream.on("GET", "", 200, "1466716163");
+        (Proxy::new(client, vec![AccessRule::new("GET", "/domain/*")]), upstream)
+    }
+
+    fn request(method: &str, url: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: "".to_string(),
+        }
+    }
+
+    fn status(proxy: &Proxy, method: &str, url: &str) -> u16 {
+        let mut buffer = Buffer::default();
+        proxy.handle(&request(method, url), &mut buffer).unwrap();
+        buffer.status
+    }
+
+    #[test]
+    fn test_forward_signed() {
+        let (proxy, upstream) = proxy();
+        assert_eq!(200, status(&proxy, "GET", "/1.0/domain/zone?x=1"));
+
+        let sent = upstream.sent.lock().unwrap();
+        assert_eq!("https://eu.api.ovh.com/1.0/domain/zone?x=1", sent[0].url);
+        assert_eq!(Some("ck"), sent[0].header("X-Ovh-Consumer"));
+        assert_eq!(true, sent[0].header("X-Ovh-Signature").is_some());
+    }
+
+    #[test]
+    fn test_streamed() {
+        let (proxy, _) = proxy();
+        let mut chunks = Chunks::default();
+        proxy.handle(&request("GET", "/1.0/domain/zone"), &mut chunks).unwrap();
+        assert_eq!(vec![b"14667".to_vec(), b"16163".to_vec()], chunks.0);
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let (proxy, upstream) = proxy();
+        assert_eq!(403, status(&proxy, "DELETE", "/1.0/domain/zone"));
+        assert_eq!(403, status(&proxy, "GET", "/1.0/me"));
+        assert_eq!(403, status(&proxy, "GET", "/1.0/domain/../me"));
+        assert_eq!(403, status(&proxy, "GET", "/1.0/domain/%2e%2e/me"));
+        assert_eq!(403, status(&proxy, "GET", "/1.0/domain/%2E./me"));
+        assert_eq!(403, status(&proxy, "GET", "/1.0/domain/zone%2F..%2F..%2Fme"));
+        assert_eq!(403, status(&proxy, "GET", "/1.0/domain/%252e%252e/me"));
+        assert_eq!(403, status(&proxy, "GET", "/1.0/domain/zone%252F..%252Fme"));
+        assert_eq!(404, status(&proxy, "GET", "/domain/zone"));
+        assert_eq!(0, upstream.sent.lock().unwrap().len());
+
+        // Escaped slashes of ids are fine
+        assert_eq!(200, status(&proxy, "GET", "/1.0/domain/1.2.3.4%2F32"));
+    }
+}
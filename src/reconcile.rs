@@ -0,0 +1,536 @@
+//! # Reconcile
+//!
+//! Converge a DNS zone to a desired record set, e.g. kept in Git: the
+//! current records are fetched, diffed with the desired ones into a
+//! `Plan` of creations, updates and deletions, which can be shown and
+//! then applied, followed by a zone refresh.
+//!
+//! NS records are never updated nor deleted, and OVH manages the SOA
+//! record of its zones, so a desired record set cannot lock a zone out
+//! of its name servers.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//! use ovh::reconcile::DesiredZone;
+//!
+//! let client = OVHClient::new();
+//! let desired = DesiredZone::load("dns/example.com.toml").unwrap();
+//! let plan = client.plan_zone("example.com", &desired.records).unwrap();
+//! print!("{}", plan);
+//! client.apply_plan(&plan).unwrap();
+//! ```
+//!
+//! Desired record sets are JSON, TOML or YAML files, `@` or an empty
+//! `subDomain` standing for the zone apex:
+//!
+//! ```toml
+//! [[records]]
+//! fieldType = "A"
+//! subDomain = "www"
+//! target = "192.0.2.1"
+//! ttl = 300
+//! ```
+//!
+extern crate serde_json;
+extern crate toml;
+extern crate yaml_rust;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use self::serde_json::Value;
+use self::yaml_rust::{Yaml, YamlLoader};
+
+use client::OVHClient;
+use dns::{FieldType, NewRecord, Record, RecordFilter};
+use error::Error;
+
+/// Records a zone should have, the others are deleted.
+#[derive(Debug,Clone,Default,PartialEq,Deserialize)]
+pub struct DesiredZone {
+    #[serde(default)]
+    pub records: Vec<NewRecord>,
+}
+
+fn invalid<E: fmt::Display>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// TOML value as JSON, to deserialize it with serde.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) |
+        toml::Value::Datetime(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => {
+            serde_json::Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null)
+        }
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Array(values) => Value::Array(values.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => {
+            Value::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect())
+        }
+    }
+}
+
+/// YAML value as JSON, to deserialize it with serde.
+fn yaml_to_json(value: Yaml) -> Result<Value, String> {
+    Ok(match value {
+        Yaml::String(s) => Value::String(s),
+        Yaml::Integer(i) => Value::from(i),
+        Yaml::Real(real) => {
+            let f = real.parse::<f64>().map_err(|_| format!("Invalid number {}", real))?;
+            serde_json::Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null)
+        }
+        Yaml::Boolean(b) => Value::Bool(b),
+        Yaml::Null => Value::Null,
+        Yaml::Array(values) => {
+            Value::Array(values.into_iter().map(yaml_to_json).collect::<Result<_, _>>()?)
+        }
+        Yaml::Hash(hash) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in hash {
+                let key = match key {
+                    Yaml::String(key) | Yaml::Real(key) => key,
+                    Yaml::Integer(key) => key.to_string(),
+                    Yaml::Boolean(key) => key.to_string(),
+                    other => return Err(format!("Unsupported key {:?}", other)),
+                };
+                object.insert(key, yaml_to_json(value)?);
+            }
+            Value::Object(object)
+        }
+        Yaml::Alias(_) => return Err("Unsupported alias".to_string()),
+        Yaml::BadValue => return Err("Invalid value".to_string()),
+    })
+}
+
+impl DesiredZone {
+    pub fn from_json(content: &str) -> io::Result<DesiredZone> {
+        serde_json::from_str(content).map_err(invalid)
+    }
+
+    pub fn from_toml(content: &str) -> io::Result<DesiredZone> {
+        let mut parser = toml::Parser::new(content);
+        let table = match parser.parse() {
+            Some(table) => table,
+            None => {
+                let reason = parser.errors
+                    .first()
+                    .map(|err| err.desc.clone())
+                    .unwrap_or_default();
+                return Err(invalid(format!("Cannot parse toml content: {}", reason)));
+            }
+        };
+        serde_json::from_value(toml_to_json(toml::Value::Table(table))).map_err(invalid)
+    }
+
+    pub fn from_yaml(content: &str) -> io::Result<DesiredZone> {
+        let documents = YamlLoader::load_from_str(content)
+            .map_err(|err| invalid(format!("Cannot parse yaml content: {}", err)))?;
+        let document = documents.into_iter().next().unwrap_or(Yaml::Null);
+        serde_json::from_value(yaml_to_json(document).map_err(invalid)?).map_err(invalid)
+    }
+
+    /// Load a `.toml`, `.json`, `.yaml` or `.yml` file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<DesiredZone> {
+        let path = path.as_ref();
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => DesiredZone::from_toml(&content),
+            Some("json") => DesiredZone::from_json(&content),
+            Some("yaml") | Some("yml") => DesiredZone::from_yaml(&content),
+            _ => {
+                Err(invalid(format!("Expected a .toml, .json or .yaml file, got {}",
+                                    path.display())))
+            }
+        }
+    }
+}
+
+/// Records a plan never updates nor deletes.
+fn is_protected(field_type: &FieldType) -> bool {
+    match *field_type {
+        FieldType::NS => true,
+        FieldType::Other(ref other) => other == "SOA",
+        _ => false,
+    }
+}
+
+fn same_name(record: &Record, desired: &NewRecord) -> bool {
+    record.field_type == desired.field_type &&
+    record.sub_domain.eq_ignore_ascii_case(&desired.sub_domain)
+}
+
+/// Same type, sub-domain and target, TTL aside.
+fn same_record(a: &NewRecord, b: &NewRecord) -> bool {
+    a.field_type == b.field_type && a.sub_domain.eq_ignore_ascii_case(&b.sub_domain) &&
+    a.target == b.target
+}
+
+/// `record` with an empty sub-domain for the zone apex, as the API has it,
+/// rather than `@` as in zone files.
+fn apex_as_empty(record: &NewRecord) -> NewRecord {
+    let mut record = record.clone();
+    if record.sub_domain == "@" {
+        record.sub_domain.clear();
+    }
+    record
+}
+
+/// `@` for the zone apex.
+fn display_name(sub_domain: &str) -> &str {
+    if sub_domain.is_empty() { "@" } else { sub_domain }
+}
+
+/// A change of a `Plan`.
+#[derive(Debug,Clone,PartialEq)]
+pub enum Change {
+    Create(NewRecord),
+    /// New target or TTL of a record, `to` keeps the id of `from`.
+    Update {
+        from: Record,
+        to: Record,
+    },
+    Delete(Record),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Change::Create(ref record) => {
+                write!(f,
+                       "+ {} {} {}",
+                       display_name(&record.sub_domain),
+                       record.field_type,
+                       record.target)?;
+                match record.ttl {
+                    Some(ttl) => write!(f, " (ttl {})", ttl),
+                    None => Ok(()),
+                }
+            }
+            Change::Update { ref from, ref to } => {
+                write!(f,
+                       "~ {} {} {}",
+                       display_name(&from.sub_domain),
+                       from.field_type,
+                       from.target)?;
+                if from.target != to.target {
+                    write!(f, " -> {}", to.target)?;
+                }
+                if from.ttl != to.ttl {
+                    write!(f, " (ttl {} -> {})", from.ttl, to.ttl)?;
+                }
+                Ok(())
+            }
+            Change::Delete(ref record) => {
+                write!(f,
+                       "- {} {} {}",
+                       display_name(&record.sub_domain),
+                       record.field_type,
+                       record.target)
+            }
+        }
+    }
+}
+
+/// Changes converging a zone to a desired record set: creations first,
+/// then updates, then deletions, so that names keep resolving.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Plan {
+    pub zone: String,
+    pub changes: Vec<Change>,
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "{}: up to date", self.zone);
+        }
+        writeln!(f, "{}: {} changes", self.zone, self.changes.len())?;
+        for change in &self.changes {
+            writeln!(f, "  {}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plan the changes from the `current` records of `zone` to the `desired` ones.
+///
+/// A desired record matches a current one of the same type, sub-domain
+/// and target, whose TTL is updated if needed. Otherwise it replaces the
+/// target of a current record of the same type and sub-domain left
+/// unmatched, or is created. Current records left unmatched are deleted,
+/// except NS records.
+pub fn diff(zone: &str, current: &[Record], desired: &[NewRecord]) -> Result<Plan, Error> {
+    let desired: Vec<NewRecord> = desired.iter().map(apex_as_empty).collect();
+    for (i, record) in desired.iter().enumerate() {
+        if let FieldType::Other(ref other) = record.field_type {
+            return Err(Error::Reconcile(format!("Unsupported record type {}", other)));
+        }
+        // Whatever their TTL, two such records would match the same current one
+        if desired[..i].iter().any(|other| same_record(other, record)) {
+            return Err(Error::Reconcile(format!("Duplicated record {}",
+                                                Change::Create(record.clone()))));
+        }
+    }
+
+    let mut creates = Vec::new();
+    let mut updates = Vec::new();
+    let mut remaining: Vec<&Record> = current.iter().collect();
+    let mut unmatched = Vec::new();
+
+    for record in &desired {
+        let found = remaining.iter()
+            .position(|current| same_name(current, record) && current.target == record.target);
+        let current = match found {
+            Some(i) => remaining.remove(i),
+            None => {
+                unmatched.push(record);
+                continue;
+            }
+        };
+        let ttl = record.ttl.unwrap_or(0);
+        if current.ttl != ttl && !is_protected(&current.field_type) {
+            let mut to = current.clone();
+            to.ttl = ttl;
+            updates.push(Change::Update {
+                from: current.clone(),
+                to: to,
+            });
+        }
+    }
+
+    for record in unmatched {
+        let found = remaining.iter()
+            .position(|current| same_name(current, record) && !is_protected(&current.field_type));
+        match found {
+            Some(i) => {
+                let current = remaining.remove(i);
+                let mut to = current.clone();
+                to.target = record.target.clone();
+                to.ttl = record.ttl.unwrap_or(0);
+                updates.push(Change::Update {
+                    from: current.clone(),
+                    to: to,
+                });
+            }
+            None => creates.push(Change::Create(record.clone())),
+        }
+    }
+
+    let deletes = remaining.into_iter()
+        .filter(|current| !is_protected(&current.field_type))
+        .map(|current| Change::Delete(current.clone()));
+
+    let mut changes = creates;
+    changes.extend(updates);
+    changes.extend(deletes);
+    Ok(Plan {
+        zone: zone.to_string(),
+        changes: changes,
+    })
+}
+
+impl OVHClient {
+    /// Changes converging `zone` to `desired`, without applying them.
+    pub fn plan_zone(&self, zone: &str, desired: &[NewRecord]) -> Result<Plan, Error> {
+        let current = self.records(zone, &RecordFilter::default())?;
+        diff(zone, &current, desired)
+    }
+
+    /// Apply the changes of `plan` in order, then refresh the zone if
+    /// there were any.
+    ///
+    /// Stops at the first failing change, the zone is then not refreshed.
+    pub fn apply_plan(&self, plan: &Plan) -> Result<(), Error> {
+        for change in &plan.changes {
+            info!("{}: {}", plan.zone, change);
+            match *change {
+                Change::Create(ref record) => {
+                    self.create_record(&plan.zone, record)?;
+                }
+                Change::Update { ref from, .. } |
+                Change::Delete(ref from) if is_protected(&from.field_type) => {
+                    return Err(Error::Reconcile(format!("Refusing to change {} record {}",
+                                                        from.field_type,
+                                                        from.id)));
+                }
+                Change::Update { ref to, .. } => self.update_record(to)?,
+                Change::Delete(ref record) => self.delete_record(&plan.zone, record.id)?,
+            }
+        }
+        if !plan.is_empty() {
+            self.refresh_zone(&plan.zone)?;
+        }
+        Ok(())
+    }
+
+    /// Plan the changes converging `zone` to `desired` and apply them,
+    /// returning the applied plan.
+    pub fn reconcile_zone(&self, zone: &str, desired: &[NewRecord]) -> Result<Plan, Error> {
+        let plan = self.plan_zone(zone, desired)?;
+        self.apply_plan(&plan)?;
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, yaml_to_json, Change, DesiredZone};
+    use super::yaml_rust::Yaml;
+    use dns::{FieldType, NewRecord, Record};
+    use dns::tests::client;
+    use error::Error;
+
+    fn record(id: u64, field_type: FieldType, sub_domain: &str, target: &str) -> Record {
+        Record {
+            id: id,
+            zone: "example.com".to_string(),
+            field_type: field_type,
+            sub_domain: sub_domain.to_string(),
+            target: target.to_string(),
+            ttl: 0,
+        }
+    }
+
+    #[test]
+    fn test_desired_zone() {
+        let from_toml = DesiredZone::from_toml(r#"
+            [[records]]
+            fieldType = "A"
+            subDomain = "www"
+            target = "192.0.2.1"
+            ttl = 300
+
+            [[records]]
+            fieldType = "MX"
+            target = "1 mx1.mail.ovh.net."
+        "#)
+            .unwrap();
+        let from_json = DesiredZone::from_json(r#"{"records": [
+            {"fieldType": "A", "subDomain": "www", "target": "192.0.2.1", "ttl": 300},
+            {"fieldType": "MX", "target": "1 mx1.mail.ovh.net."}
+        ]}"#)
+            .unwrap();
+        assert_eq!(from_json, from_toml);
+        assert_eq!(Some(300), from_toml.records[0].ttl);
+        assert_eq!("", from_toml.records[1].sub_domain);
+        assert_eq!(true, DesiredZone::from_toml("[[records]]\nfieldType = ").is_err());
+
+        let from_yaml = DesiredZone::from_yaml("
+records:
+  - fieldType: A
+    subDomain: www
+    target: 192.0.2.1
+    ttl: 300
+  - fieldType: MX
+    target: 1 mx1.mail.ovh.net.
+")
+            .unwrap();
+        assert_eq!(from_json, from_yaml);
+        assert_eq!(true, DesiredZone::from_yaml("records: [").is_err());
+        assert_eq!(Err("Unsupported alias".to_string()), yaml_to_json(Yaml::Alias(0)));
+        assert_eq!(Err("Invalid value".to_string()), yaml_to_json(Yaml::BadValue));
+    }
+
+    #[test]
+    fn test_diff() {
+        let current = vec![record(1, FieldType::NS, "", "dns200.anycast.me."),
+                           record(2, FieldType::A, "www", "192.0.2.1"),
+                           record(3, FieldType::A, "www", "192.0.2.9"),
+                           record(4, FieldType::MX, "", "1 mx1.mail.ovh.net."),
+                           record(5, FieldType::TXT, "ftp", "\"old\"")];
+        let mut www = NewRecord::new(FieldType::A, "WWW", "192.0.2.1");
+        www.ttl = Some(300);
+        let desired = vec![www,
+                           NewRecord::new(FieldType::A, "www", "192.0.2.2"),
+                           NewRecord::new(FieldType::MX, "", "1 mx1.mail.ovh.net."),
+                           NewRecord::new(FieldType::CNAME, "blog", "example.com.")];
+
+        let plan = diff("example.com", &current, &desired).unwrap();
+        let mut ttl = current[1].clone();
+        ttl.ttl = 300;
+        let mut target = current[2].clone();
+        target.target = "192.0.2.2".to_string();
+        assert_eq!(vec![Change::Create(desired[3].clone()),
+                        Change::Update {
+                            from: current[1].clone(),
+                            to: ttl,
+                        },
+                        Change::Update {
+                            from: current[2].clone(),
+                            to: target,
+                        },
+                        Change::Delete(current[4].clone())],
+                   plan.changes);
+        assert_eq!("example.com: 4 changes
+  + blog CNAME example.com.
+  ~ www A 192.0.2.1 (ttl 0 -> 300)
+  ~ www A 192.0.2.9 -> 192.0.2.2
+  - ftp TXT \"old\"
+",
+                   plan.to_string());
+
+        let converged = diff("example.com", &current[..1], &[]).unwrap();
+        assert_eq!(true, converged.is_empty());
+        let unknown = NewRecord::new(FieldType::Other("SOA".to_string()), "", "x");
+        assert_eq!(true, diff("example.com", &current, &[unknown]).is_err());
+    }
+
+    #[test]
+    fn test_diff_apex_and_duplicates() {
+        let current = vec![record(4, FieldType::MX, "", "1 mx1.mail.ovh.net.")];
+        let apex = NewRecord::new(FieldType::MX, "@", "1 mx1.mail.ovh.net.");
+        assert_eq!(true, diff("example.com", &current, &[apex.clone()]).unwrap().is_empty());
+
+        let mut other_ttl = apex.clone();
+        other_ttl.ttl = Some(60);
+        match diff("example.com", &current, &[apex, other_ttl]) {
+            Err(Error::Reconcile(message)) => {
+                assert_eq!("Duplicated record + @ MX 1 mx1.mail.ovh.net. (ttl 60)", message)
+            }
+            other => panic!("expected a duplicated record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let (client, api) = client(vec![
+            ("GET", "/domain/zone/example.com/record", "[1, 2]"),
+            ("GET", "/record/1", r#"{"id": 1, "zone": "example.com", "fieldType": "NS",
+                                     "subDomain": "", "target": "dns200.anycast.me.", "ttl": 0}"#),
+            ("GET", "/record/2", r#"{"id": 2, "zone": "example.com", "fieldType": "A",
+                                     "subDomain": "www", "target": "192.0.2.1", "ttl": 0}"#),
+            ("DELETE", "/domain/zone/example.com/record/2", ""),
+            ("POST", "/domain/zone/example.com/refresh", ""),
+        ]);
+        let plan = client.reconcile_zone("example.com", &[]).unwrap();
+        assert_eq!(1, plan.changes.len());
+        {
+            let sent = api.sent.lock().unwrap();
+            let calls: Vec<String> = sent.iter().skip(3).map(|r| r.method.clone()).collect();
+            assert_eq!(vec!["DELETE", "POST"], calls);
+            assert_eq!(true, sent[4].url.ends_with("/refresh"));
+        }
+
+        let mut protected = plan.clone();
+        protected.changes = vec![Change::Delete(record(1, FieldType::NS, "", "dns200.anycast.me."))];
+        match client.apply_plan(&protected) {
+            Err(Error::Reconcile(message)) => {
+                assert_eq!("Refusing to change NS record 1", message)
+            }
+            other => panic!("expected a refused change, got {:?}", other),
+        }
+        assert_eq!(5, api.sent.lock().unwrap().len());
+    }
+}
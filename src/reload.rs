@@ -0,0 +1,118 @@
+//! # Reload
+//!
+//! Keep a `Credential` in sync with the file it was read from,
+//! e.g. when consumer keys are rotated by an external job.
+//!
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use config::Credential;
+
+struct State {
+    modified: Option<SystemTime>,
+    credential: Credential,
+}
+
+/// Source of a `Credential` re-read whenever its file changes.
+///
+/// Readers always get a complete `Credential`: a new one is swapped in only
+/// once the file has been read and parsed successfully, otherwise the
+/// previous one is kept.
+pub struct Reloader {
+    path: PathBuf,
+    state: RwLock<State>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl Reloader {
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Reloader> {
+        let path = path.as_ref().to_path_buf();
+        let modified = modified(&path);
+        let credential = Credential::load_from_file(&path)?;
+        Ok(Reloader {
+            path: path,
+            state: RwLock::new(State {
+                modified: modified,
+                credential: credential,
+            }),
+        })
+    }
+
+    /// Current `Credential`, re-read first if the file was modified.
+    pub fn current(&self) -> Credential {
+        {
+            let state = self.state.read().unwrap();
+            if state.modified == modified(&self.path) {
+                return state.credential.clone();
+            }
+        }
+        self.reload()
+    }
+
+    /// Re-read the file unconditionally, e.g. after the API rejected
+    /// a signature, and return the resulting `Credential`.
+    pub fn reload(&self) -> Credential {
+        let modified = modified(&self.path);
+        let mut state = self.state.write().unwrap();
+        match Credential::load_from_file(&self.path) {
+            Ok(credential) => {
+                info!("Reloaded credential from {}", self.path.display());
+                state.credential = credential;
+            }
+            Err(err) => {
+                warn!("Cannot reload credential from {}: {}", self.path.display(), err);
+            }
+        }
+        // Do not retry a broken file until it changes again
+        state.modified = modified;
+        state.credential.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::Path;
+
+    use super::Reloader;
+
+    fn write_config(path: &Path, consumer_key: &str) {
+        let mut fd = File::create(path).unwrap();
+        write!(fd,
+               "[default]\nendpoint = \"ovh-eu\"\n\n[ovh-eu]\napplication_key = \"ak\"\n\
+                application_secret = \"as\"\nconsumer_key = \"{}\"\n",
+               consumer_key)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_reload() {
+        let path = env::temp_dir().join("ovh-rs-test-reload.toml");
+        write_config(&path, "ck1");
+        let reloader = Reloader::new(&path).unwrap();
+        assert_eq!("ck1", reloader.current().consumer_key.expose());
+
+        write_config(&path, "ck2");
+        assert_eq!("ck2", reloader.reload().consumer_key.expose());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reload_keeps_previous_on_error() {
+        let path = env::temp_dir().join("ovh-rs-test-reload-broken.toml");
+        write_config(&path, "ck1");
+        let reloader = Reloader::new(&path).unwrap();
+
+        File::create(&path).unwrap().write_all(b"[default").unwrap();
+        assert_eq!("ck1", reloader.reload().consumer_key.expose());
+        fs::remove_file(&path).unwrap();
+    }
+}
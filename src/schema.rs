@@ -0,0 +1,406 @@
+//! # Schema
+//!
+//! Machine-readable descriptions OVH publishes for each of its APIs,
+//! e.g. `https://eu.api.ovh.com/1.0/domain.json`: paths, operations,
+//! their parameters, and the models and enums they use.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//!
+//! let client = OVHClient::new();
+//! let schema = client.fetch_schema("domain").unwrap();
+//! let found = schema.operation("GET", "/domain/zone/example.com/record").unwrap();
+//! println!("{}: {:?}", found.api.path, found.operation.description);
+//! ```
+//!
+extern crate serde_json;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer};
+use self::serde_json::Value;
+
+use client::OVHClient;
+use error::Error;
+
+/// Primitive types whose values are JSON strings.
+pub(crate) const STRING_TYPES: [&'static str; 17] =
+    ["string", "password", "text", "date", "datetime", "time", "ip", "ipBlock", "ipv4",
+     "ipv6", "ipv4Block", "ipv6Block", "macAddress", "phoneNumber", "internationalPhoneNumber",
+     "uuid", "duration"];
+
+/// Accept `true`/`false` as well as `1`/`0`, both are found in schemas.
+fn flag<D: Deserializer>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match <Value as Deserialize>::deserialize(deserializer)? {
+        Value::Bool(flag) => flag,
+        Value::Number(n) => n.as_u64().map(|n| n != 0).unwrap_or(false),
+        _ => false,
+    })
+}
+
+/// Description of one API, e.g. `/domain`.
+#[derive(Debug,Clone,Deserialize)]
+pub struct Schema {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    /// e.g. `/domain`
+    #[serde(rename = "resourcePath")]
+    pub resource_path: String,
+    #[serde(rename = "basePath")]
+    pub base_path: String,
+    pub apis: Vec<Api>,
+    /// Models and enums, by full name, e.g. `domain.zone.Record`.
+    #[serde(default)]
+    pub models: BTreeMap<String, Model>,
+}
+
+/// A path and the operations it supports.
+#[derive(Debug,Clone,Deserialize)]
+pub struct Api {
+    /// Path template, e.g. `/domain/zone/{zoneName}/record`.
+    pub path: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Debug,Clone,Deserialize)]
+pub struct Operation {
+    #[serde(rename = "httpMethod")]
+    pub http_method: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "apiStatus", default)]
+    pub api_status: Option<ApiStatus>,
+    /// Whether the call is made without consumer key.
+    #[serde(rename = "noAuthentication", default, deserialize_with = "flag")]
+    pub no_authentication: bool,
+    #[serde(default)]
+    pub parameters: Vec<Parameter>,
+    /// e.g. `long[]` or `domain.zone.Record`.
+    #[serde(rename = "responseType")]
+    pub response_type: String,
+}
+
+impl Operation {
+    /// Parameters of this operation taken from `place`.
+    pub fn parameters_in(&self, place: ParamType) -> Vec<&Parameter> {
+        self.parameters.iter().filter(|p| p.param_type == place).collect()
+    }
+}
+
+/// Lifecycle of an operation, e.g. `PRODUCTION` or `DEPRECATED`.
+#[derive(Debug,Clone,Deserialize)]
+pub struct ApiStatus {
+    pub value: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// When a deprecated operation is removed.
+    #[serde(rename = "deletionDate", default)]
+    pub deletion_date: Option<String>,
+    #[serde(default)]
+    pub replacement: Option<String>,
+}
+
+/// Where a parameter is given.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Deserialize)]
+pub enum ParamType {
+    #[serde(rename = "path")]
+    Path,
+    #[serde(rename = "query")]
+    Query,
+    #[serde(rename = "body")]
+    Body,
+    #[serde(rename = "header")]
+    Header,
+}
+
+#[derive(Debug,Clone,Deserialize)]
+pub struct Parameter {
+    /// `None` for a body parameter standing for the whole body.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// e.g. `string`, `long`, `ipBlock` or `zone.NamedResolutionFieldTypeEnum`.
+    #[serde(rename = "dataType")]
+    pub data_type: String,
+    #[serde(rename = "paramType")]
+    pub param_type: ParamType,
+    #[serde(rename = "fullType", default)]
+    pub full_type: Option<String>,
+    #[serde(default, deserialize_with = "flag")]
+    pub required: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// A model (with `properties`) or an enum (with `enum_values`).
+#[derive(Debug,Clone,Deserialize)]
+pub struct Model {
+    pub id: String,
+    pub namespace: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "enum", default)]
+    pub enum_values: Option<Vec<String>>,
+    #[serde(rename = "enumType", default)]
+    pub enum_type: Option<String>,
+    /// Type parameters of generic models, e.g. `T` of `complexType.UnitAndValue<T>`.
+    #[serde(default)]
+    pub generics: Option<Vec<String>>,
+    #[serde(default)]
+    pub properties: BTreeMap<String, Property>,
+}
+
+impl Model {
+    pub fn is_enum(&self) -> bool {
+        self.enum_values.is_some()
+    }
+}
+
+#[derive(Debug,Clone,Deserialize)]
+pub struct Property {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(rename = "fullType", default)]
+    pub full_type: Option<String>,
+    #[serde(rename = "canBeNull", default, deserialize_with = "flag")]
+    pub can_be_null: bool,
+    #[serde(rename = "readOnly", default, deserialize_with = "flag")]
+    pub read_only: bool,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// The operation found for a call, see `Schema::operation`.
+#[derive(Debug,Clone)]
+pub struct Lookup<'a> {
+    pub api: &'a Api,
+    pub operation: &'a Operation,
+    /// Values of the path parameters, e.g. `("zoneName", "example.com")`.
+    pub path_params: Vec<(String, String)>,
+}
+
+/// Match `path` against a path template, returning the path parameters.
+fn match_template(template: &str, path: &str) -> Option<Vec<(String, String)>> {
+    let template: Vec<&str> = template.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    if template.len() != path.len() {
+        return None;
+    }
+    let mut params = Vec::new();
+    for (expected, actual) in template.iter().zip(path.iter()) {
+        if expected.starts_with('{') && expected.ends_with('}') {
+            if actual.is_empty() {
+                return None;
+            }
+            params.push((expected[1..expected.len() - 1].to_string(), actual.to_string()));
+        } else if expected != actual {
+            return None;
+        }
+    }
+    Some(params)
+}
+
+impl Schema {
+    /// Parse a schema, e.g. the content of `/1.0/domain.json`.
+    pub fn parse(content: &str) -> Result<Schema, Error> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    /// Load a schema saved to a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Schema> {
+        let mut content = String::new();
+        File::open(path)?.read_to_string(&mut content)?;
+        serde_json::from_str(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    /// Operation for a `method` call to `path` (query string ignored),
+    /// preferring literal segments over path parameters when several
+    /// templates match, e.g. `/me/bill/export` over `/me/bill/{billId}`.
+    pub fn operation(&self, method: &str, path: &str) -> Option<Lookup> {
+        let path = path.split('?').next().unwrap_or("");
+        let mut found: Option<Lookup> = None;
+        for api in &self.apis {
+            let params = match match_template(&api.path, path) {
+                Some(params) => params,
+                None => continue,
+            };
+            let operation = match api.operations
+                .iter()
+                .find(|operation| operation.http_method.eq_ignore_ascii_case(method)) {
+                Some(operation) => operation,
+                None => continue,
+            };
+            let better = match found {
+                Some(ref best) => params.len() < best.path_params.len(),
+                None => true,
+            };
+            if better {
+                found = Some(Lookup {
+                    api: api,
+                    operation: operation,
+                    path_params: params,
+                });
+            }
+        }
+        found
+    }
+
+    /// The `Api` of a path template, e.g. `/domain/zone/{zoneName}`.
+    pub fn api(&self, template: &str) -> Option<&Api> {
+        self.apis.iter().find(|api| api.path == template)
+    }
+
+    /// Model or enum by full name, e.g. `domain.zone.Record`.
+    pub fn model(&self, name: &str) -> Option<&Model> {
+        self.models.get(name)
+    }
+}
+
+impl OVHClient {
+    /// Download the schema of `api`, e.g. `domain` or `/dedicated/server`,
+    /// from the client's endpoint.
+    pub fn fetch_schema(&self, api: &str) -> Result<Schema, Error> {
+        let query = format!("/{}.json", api.trim_matches('/'));
+        let response = self.execute_unauthenticated("GET", &query, "")?.error_for_status()?;
+        Schema::parse(&response.body)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{ParamType, Schema};
+
+    /// Excerpt of `/1.0/domain.json`.
+    pub const DOMAIN: &'static str = r#"{
+        "apiVersion": "1.0",
+        "resourcePath": "/domain",
+        "basePath": "https://eu.api.ovh.com/1.0",
+        "apis": [
+            {"path": "/domain/zone/{zoneName}/record", "description": "Records",
+             "operations": [
+                {"httpMethod": "GET", "noAuthentication": false,
+                 "apiStatus": {"value": "PRODUCTION", "description": "Stable production version"},
+                 "description": "Records of the zone", "responseType": "long[]",
+                 "parameters": [
+                    {"name": "zoneName", "dataType": "string", "paramType": "path",
+                     "fullType": "string", "required": true, "description": "The zone"},
+                    {"name": "fieldType", "dataType": "zone.NamedResolutionFieldTypeEnum",
+                     "paramType": "query", "fullType": "zone.NamedResolutionFieldTypeEnum",
+                     "required": false, "description": "Filter the value of fieldType (=)"},
+                    {"name": "subDomain", "dataType": "string", "paramType": "query",
+                     "fullType": "string", "required": false, "description": null}
+                 ]},
+                {"httpMethod": "POST", "noAuthentication": false,
+                 "description": "Create a new DNS record", "responseType": "domain.zone.Record",
+                 "parameters": [
+                    {"name": "zoneName", "dataType": "string", "paramType": "path",
+                     "fullType": "string", "required": 1},
+                    {"name": "fieldType", "dataType": "zone.NamedResolutionFieldTypeEnum",
+                     "paramType": "body", "fullType": "zone.NamedResolutionFieldTypeEnum",
+                     "required": 1},
+                    {"name": "target", "dataType": "string", "paramType": "body",
+                     "fullType": "string", "required": 1},
+                    {"name": "subDomain", "dataType": "string", "paramType": "body",
+                     "fullType": "string", "required": 0},
+                    {"name": "ttl", "dataType": "long", "paramType": "body",
+                     "fullType": "long", "required": 0}
+                 ]}
+             ]},
+            {"path": "/domain/zone/{zoneName}/record/{id}", "description": "Record",
+             "operations": [
+                {"httpMethod": "GET", "description": "Get this object properties",
+                 "responseType": "domain.zone.Record",
+                 "parameters": [
+                    {"name": "zoneName", "dataType": "string", "paramType": "path", "required": 1},
+                    {"name": "id", "dataType": "long", "paramType": "path", "required": 1}
+                 ]},
+                {"httpMethod": "DELETE", "description": "Delete a DNS record",
+                 "responseType": "void",
+                 "parameters": [
+                    {"name": "zoneName", "dataType": "string", "paramType": "path", "required": 1},
+                    {"name": "id", "dataType": "long", "paramType": "path", "required": 1}
+                 ]}
+             ]},
+            {"path": "/domain/zone/{zoneName}/record/export", "description": "Export",
+             "operations": [
+                {"httpMethod": "GET", "description": "Export zone", "responseType": "text",
+                 "parameters": [
+                    {"name": "zoneName", "dataType": "string", "paramType": "path", "required": 1}
+                 ]}
+             ]}
+        ],
+        "models": {
+            "zone.NamedResolutionFieldTypeEnum": {
+                "id": "NamedResolutionFieldTypeEnum", "namespace": "zone",
+                "description": "Resource record fieldType",
+                "enum": ["A", "AAAA", "CNAME", "MX", "NS", "TXT"], "enumType": "string"
+            },
+            "domain.zone.Record": {
+                "id": "Record", "namespace": "domain.zone", "description": "Zone resource records",
+                "properties": {
+                    "fieldType": {"type": "zone.NamedResolutionFieldTypeEnum",
+                                  "fullType": "zone.NamedResolutionFieldTypeEnum",
+                                  "canBeNull": false, "readOnly": true,
+                                  "description": "Resource record Name"},
+                    "id": {"type": "long", "fullType": "long", "canBeNull": false,
+                           "readOnly": 1, "description": "Id of the zone resource record"},
+                    "subDomain": {"type": "string", "fullType": "string", "canBeNull": true,
+                                  "readOnly": false, "description": "Resource record subdomain"},
+                    "target": {"type": "string", "fullType": "string", "canBeNull": false,
+                               "readOnly": false, "description": "Resource record target"},
+                    "ttl": {"type": "long", "fullType": "long", "canBeNull": true,
+                            "readOnly": false, "description": "Resource record ttl"},
+                    "zone": {"type": "string", "fullType": "string", "canBeNull": false,
+                             "readOnly": true, "description": "Resource record zone"}
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_parse() {
+        let schema = Schema::parse(DOMAIN).unwrap();
+        assert_eq!("/domain", schema.resource_path);
+        assert_eq!(3, schema.apis.len());
+        let post = &schema.apis[0].operations[1];
+        assert_eq!(true, post.parameters[0].required);
+        assert_eq!(false, post.parameters[3].required);
+        assert_eq!(4, post.parameters_in(ParamType::Body).len());
+
+        let fields = schema.model("zone.NamedResolutionFieldTypeEnum").unwrap();
+        assert_eq!(true, fields.is_enum());
+        let record = schema.model("domain.zone.Record").unwrap();
+        assert_eq!(true, record.properties["id"].read_only);
+        assert_eq!(true, record.properties["subDomain"].can_be_null);
+    }
+
+    #[test]
+    fn test_operation() {
+        let schema = Schema::parse(DOMAIN).unwrap();
+        let found = schema.operation("GET", "/domain/zone/example.com/record?fieldType=A")
+            .unwrap();
+        assert_eq!("/domain/zone/{zoneName}/record", found.api.path);
+        assert_eq!("GET", found.operation.http_method);
+        assert_eq!(vec![("zoneName".to_string(), "example.com".to_string())],
+                   found.path_params);
+
+        let found = schema.operation("delete", "/domain/zone/example.com/record/42").unwrap();
+        assert_eq!(2, found.path_params.len());
+
+        assert_eq!(None, schema.operation("PUT", "/domain/zone/example.com/record")
+                             .map(|found| found.api.path.clone()));
+        assert_eq!(true, schema.operation("GET", "/domain/zone//record").is_none());
+    }
+
+    #[test]
+    fn test_literal_segments_first() {
+        let schema = Schema::parse(DOMAIN).unwrap();
+        let found = schema.operation("GET", "/domain/zone/example.com/record/export").unwrap();
+        assert_eq!("/domain/zone/{zoneName}/record/export", found.api.path);
+    }
+}
@@ -0,0 +1,93 @@
+//! # Secret
+//!
+//! Holder for credential values that must never show up in logs:
+//! redacted on `Debug` and `Display`, wiped from memory on drop.
+//!
+use std::fmt;
+
+use crypto::util::fixed_time_eq;
+use zeroize::Zeroize;
+
+#[derive(Clone,Default)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new<S: Into<String>>(value: S) -> Secret {
+        Secret(value.into())
+    }
+
+    /// Access the secret value, e.g. to sign a request.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "***")
+    }
+}
+
+/// Compared in constant time, not to leak how much of a guess is right.
+impl PartialEq for Secret {
+    fn eq(&self, other: &Secret) -> bool {
+        fixed_time_eq(self.0.as_bytes(), other.0.as_bytes())
+    }
+}
+
+impl Eq for Secret {}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<'a> From<&'a str> for Secret {
+    fn from(value: &'a str) -> Secret {
+        Secret::new(value)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Secret {
+        Secret(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Secret;
+
+    #[test]
+    fn test_redacted() {
+        let secret = Secret::new("topsecret");
+        assert_eq!("Secret(***)", format!("{:?}", secret));
+        assert_eq!("***", format!("{}", secret));
+    }
+
+    #[test]
+    fn test_expose() {
+        let secret = Secret::from("topsecret");
+        assert_eq!("topsecret", secret.expose());
+        assert_eq!(false, secret.is_empty());
+        assert_eq!(true, Secret::default().is_empty());
+    }
+
+    #[test]
+    fn test_eq() {
+        assert_eq!(true, Secret::new("topsecret") == Secret::new("topsecret"));
+        assert_eq!(false, Secret::new("topsecret") == Secret::new("topsecreT"));
+        assert_eq!(false, Secret::new("topsecret") == Secret::new("top"));
+    }
+}
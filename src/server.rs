@@ -0,0 +1,159 @@
+//! # Server
+//!
+//! Minimal HTTP/1.1 plumbing for the local servers of this crate:
+//! one request per connection, bodies sized by `Content-Length`.
+//!
+extern crate serde_json;
+
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+use client::{Request, Response};
+
+/// Largest request body read, larger ones are answered with a 413.
+pub const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Reason of the error returned by `read_request` for too large bodies.
+#[derive(Debug)]
+struct BodyTooLarge(usize);
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Body of {} bytes exceeds {} bytes", self.0, MAX_BODY_SIZE)
+    }
+}
+
+impl error::Error for BodyTooLarge {
+    fn description(&self) -> &str {
+        "Body too large"
+    }
+}
+
+/// Read a request from `stream`.
+///
+/// The `url` of the returned `Request` is the request target as sent,
+/// i.e. the path and the query string.
+///
+/// Bodies larger than `MAX_BODY_SIZE` are not read, see `reject`.
+pub fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let (method, target) = {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Malformed request line")),
+        }
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(pos) = header.find(':') {
+            headers.push((header[..pos].trim().to_string(), header[pos + 1..].trim().to_string()));
+        }
+    }
+
+    let length = headers.iter()
+        .find(|&&(ref name, _)| name.eq_ignore_ascii_case("Content-Length"))
+        .and_then(|&(_, ref value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData, BodyTooLarge(length)));
+    }
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8(body)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Body is not UTF-8"))?;
+
+    Ok(Request {
+        method: method,
+        url: target,
+        headers: headers,
+        body: body,
+    })
+}
+
+/// Write `response` to `stream`, then the connection is to be closed.
+pub fn write_response(mut stream: &TcpStream, response: &Response) -> io::Result<()> {
+    write_head(stream,
+               response.status,
+               &response.headers,
+               Some(response.body.len()))?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+/// Write the status line and headers of a response to `stream`.
+///
+/// Without `length`, the body ends when the connection is closed, e.g.
+/// to write it as it is received.
+pub fn write_head(mut stream: &TcpStream,
+                  status: u16,
+                  headers: &[(String, String)],
+                  length: Option<usize>)
+                  -> io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason(status));
+    for &(ref name, ref value) in headers {
+        let hop_by_hop = ["Content-Length", "Connection", "Transfer-Encoding"]
+            .iter()
+            .any(|h| name.eq_ignore_ascii_case(h));
+        if !hop_by_hop {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    if let Some(length) = length {
+        head.push_str(&format!("Content-Length: {}\r\n", length));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+    stream.write_all(head.as_bytes())
+}
+
+/// Response to a request `read_request` failed to read: 413 if its body
+/// was too large, 400 otherwise.
+pub fn reject(err: &io::Error) -> Response {
+    let too_large = err.get_ref().map_or(false, |inner| inner.is::<BodyTooLarge>());
+    error_response(if too_large { 413 } else { 400 }, &err.to_string())
+}
+
+/// A JSON response shaped like OVH's errors.
+pub fn error_response(status: u16, message: &str) -> Response {
+    let body = format!("{{\"httpCode\":\"{} {}\",\"message\":{}}}",
+                       status,
+                       reason(status),
+                       serde_json::to_string(message).unwrap());
+    Response {
+        status: status,
+        headers: vec![("Content-Type".to_string(), "application/json; charset=utf-8".to_string())],
+        body: body,
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
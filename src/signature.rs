@@ -0,0 +1,151 @@
+//! # Signature
+//!
+//! Compute and verify the `X-Ovh-Signature` header of a request:
+//! `$1$` followed by the hex SHA1 of
+//! `application_secret+consumer_key+method+url+body+timestamp`.
+//!
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use crypto::util::fixed_time_eq;
+use zeroize::Zeroize;
+
+use client::Request;
+
+/// The signed parts of a request.
+#[derive(Debug,Clone,PartialEq)]
+pub struct Payload<'a> {
+    pub method: &'a str,
+    /// Full url, including the query string.
+    pub url: &'a str,
+    pub body: &'a str,
+    pub timestamp: &'a str,
+}
+
+impl<'a> Payload<'a> {
+    /// Signed parts of a request built by `OVHClient`, `None` if the
+    /// request has no `X-Ovh-Timestamp` header.
+    pub fn from_request(request: &'a Request) -> Option<Payload<'a>> {
+        request.header("X-Ovh-Timestamp").map(|timestamp| {
+            Payload {
+                method: &request.method,
+                url: &request.url,
+                body: &request.body,
+                timestamp: timestamp,
+            }
+        })
+    }
+}
+
+/// Compute the signature of `payload`.
+pub fn compute(payload: &Payload, application_secret: &str, consumer_key: &str) -> String {
+    let sep = "+";
+    let prefix = "$1$".to_string();
+
+    let capacity = application_secret.len() + consumer_key.len() + payload.method.len() +
+                   payload.url.len() + payload.body.len() +
+                   payload.timestamp.len() + 5 * sep.len();
+    let mut signature = String::with_capacity(capacity);
+    signature.push_str(application_secret);
+    signature.push_str(sep);
+    signature.push_str(consumer_key);
+    signature.push_str(sep);
+    signature.push_str(payload.method);
+    signature.push_str(sep);
+    signature.push_str(payload.url);
+    signature.push_str(sep);
+    signature.push_str(payload.body);
+    signature.push_str(sep);
+    signature.push_str(payload.timestamp);
+
+    let mut hasher = Sha1::new();
+    hasher.input_str(&signature);
+    // the pre-hash string embeds the application secret
+    signature.zeroize();
+    let hex = hasher.result_str();
+    debug!("hex: {}", &hex);
+
+    prefix + &hex
+}
+
+/// Check `signature` against the one of `payload`, in constant time.
+pub fn verify(payload: &Payload,
+              application_secret: &str,
+              consumer_key: &str,
+              signature: &str)
+              -> bool {
+    if signature.is_empty() {
+        return false;
+    }
+    let expected = compute(payload, application_secret, consumer_key);
+    fixed_time_eq(expected.as_bytes(), signature.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compute, verify, Payload};
+    use client::Request;
+
+    const AAS: &'static str = "somesecret";
+    const CK: &'static str = "fakeconsumerkey";
+
+    fn payload<'a>(method: &'a str, url: &'a str, body: &'a str, timestamp: &'a str) -> Payload<'a> {
+        Payload {
+            method: method,
+            url: url,
+            body: body,
+            timestamp: timestamp,
+        }
+    }
+
+    #[test]
+    fn test_vectors() {
+        let vectors = vec![
+            (payload("GET", "https://eu.api.ovh.com/1.0/ipLoadbalancing", "", "1466716163"),
+             CK,
+             "$1$7ff04a6c8610e4f96a1c0a04dff50ed760a6b724"),
+            (payload("POST",
+                     "https://eu.api.ovh.com/1.0/order/cart",
+                     "{\"ovhSubsidiary\": \"FR\"}",
+                     "1466716163"),
+             CK,
+             "$1$3e85114a83d299d112fe55180d903cfa3ea12200"),
+            (payload("DELETE", "https://eu.api.ovh.com/1.0/order/cart/abc", "", "1466716200"),
+             CK,
+             "$1$fcd813af5fd6a6b20d7567f4e2946eeaf89f3913"),
+            // no consumer key yet, with a query string
+            (payload("GET",
+                     "https://ca.api.ovh.com/1.0/domain/zone/example.com/record?fieldType=A",
+                     "",
+                     "1500000000"),
+             "",
+             "$1$b106b6928e4f481bedfb4bdcf965b53bc4bccecf"),
+        ];
+        for (payload, ck, expected) in vectors {
+            assert_eq!(expected, compute(&payload, AAS, ck));
+            assert_eq!(true, verify(&payload, AAS, ck, expected));
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects() {
+        let payload = payload("GET", "https://eu.api.ovh.com/1.0/ipLoadbalancing", "", "1466716163");
+        let good = "$1$7ff04a6c8610e4f96a1c0a04dff50ed760a6b724";
+        assert_eq!(false, verify(&payload, "othersecret", CK, good));
+        assert_eq!(false, verify(&payload, AAS, "otherconsumer", good));
+        assert_eq!(false, verify(&payload, AAS, CK, "$1$7ff04a"));
+        assert_eq!(false, verify(&payload, AAS, CK, ""));
+    }
+
+    #[test]
+    fn test_payload_from_request() {
+        let request = Request {
+            method: "GET".to_string(),
+            url: "https://eu.api.ovh.com/1.0/ipLoadbalancing".to_string(),
+            headers: vec![("X-Ovh-Timestamp".to_string(), "1466716163".to_string())],
+            body: "".to_string(),
+        };
+        let payload = Payload::from_request(&request).unwrap();
+        assert_eq!("$1$7ff04a6c8610e4f96a1c0a04dff50ed760a6b724",
+                   compute(&payload, AAS, CK));
+    }
+}
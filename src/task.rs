@@ -0,0 +1,262 @@
+//! # Task
+//!
+//! Many OVH operations (reboot, reinstall, zone refresh, IP move...)
+//! return a task that must be polled until it reaches a final status.
+//!
+extern crate serde_json;
+
+use std::cmp;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use self::serde_json::Value;
+
+use client::OVHClient;
+use error::Error;
+
+/// Status of an OVH task, as reported by its `status` (or `state`) field.
+#[derive(Debug,Clone,PartialEq)]
+pub enum TaskStatus {
+    Init,
+    Todo,
+    Doing,
+    Done,
+    Error,
+    Cancelled,
+    /// Any status this crate does not know about yet.
+    Unknown(String),
+}
+
+impl TaskStatus {
+    /// Map a status as returned by the API.
+    ///
+    /// Dedicated servers and IPs split failures into `customerError`
+    /// and `ovhError`, both are considered as `Error`.
+    pub fn from_api(status: &str) -> TaskStatus {
+        match status {
+            "init" => TaskStatus::Init,
+            "todo" => TaskStatus::Todo,
+            "doing" => TaskStatus::Doing,
+            "done" => TaskStatus::Done,
+            "error" | "customerError" | "ovhError" => TaskStatus::Error,
+            "cancelled" => TaskStatus::Cancelled,
+            other => TaskStatus::Unknown(other.to_string()),
+        }
+    }
+
+    /// Whether polling can stop.
+    pub fn is_final(&self) -> bool {
+        match *self {
+            TaskStatus::Done | TaskStatus::Error | TaskStatus::Cancelled => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TaskStatus::Init => write!(f, "init"),
+            TaskStatus::Todo => write!(f, "todo"),
+            TaskStatus::Doing => write!(f, "doing"),
+            TaskStatus::Done => write!(f, "done"),
+            TaskStatus::Error => write!(f, "error"),
+            TaskStatus::Cancelled => write!(f, "cancelled"),
+            TaskStatus::Unknown(ref status) => write!(f, "{}", status),
+        }
+    }
+}
+
+/// Polling settings for `OVHClient::wait_for_task`.
+#[derive(Debug,Clone)]
+pub struct WaitOptions {
+    /// Delay before the second poll, doubled after each poll.
+    pub initial_delay: Duration,
+    /// Upper bound of the delay between two polls.
+    pub max_delay: Duration,
+    /// Give up once this much time has elapsed.
+    pub timeout: Duration,
+}
+
+impl Default for WaitOptions {
+    fn default() -> WaitOptions {
+        WaitOptions {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            timeout: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Extract the status of a task object.
+fn task_status(task: &Value) -> TaskStatus {
+    let status = task.get("status")
+        .or_else(|| task.get("state"))
+        .and_then(|s| s.as_str())
+        .unwrap_or("");
+    TaskStatus::from_api(status)
+}
+
+/// Call `attempt` until it returns a value, waiting between two calls
+/// as set by `options`.
+///
+/// Stops at the first error, returns `None` once `options.timeout` has elapsed.
+pub(crate) fn poll<T, F>(options: &WaitOptions, mut attempt: F) -> Result<Option<T>, Error>
+    where F: FnMut() -> Result<Option<T>, Error>
+{
+    let start = Instant::now();
+    let mut delay = options.initial_delay;
+    loop {
+        if let Some(value) = attempt()? {
+            return Ok(Some(value));
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= options.timeout {
+            return Ok(None);
+        }
+        thread::sleep(cmp::min(delay, options.timeout - elapsed));
+        delay = cmp::min(delay * 2, options.max_delay);
+    }
+}
+
+impl OVHClient {
+    /// Poll the task at `path` (e.g. `/dedicated/server/{name}/task/{id}`)
+    /// until it is `done`, `error` or `cancelled`.
+    ///
+    /// `progress` is called with the status and the task object after each poll.
+    /// Returns the last task object once done, fails as soon as the task
+    /// cannot be read (e.g. `Error::Api` for a 404).
+    pub fn wait_for_task<F>(&self,
+                            path: &str,
+                            options: &WaitOptions,
+                            mut progress: F)
+                            -> Result<Value, Error>
+        where F: FnMut(&TaskStatus, &Value)
+    {
+        let task = poll(options, || {
+            let response = self.execute("GET", path, "")?.error_for_status()?;
+            let task: Value = serde_json::from_str(&response.body)?;
+            let status = task_status(&task);
+            debug!("Task {}: {}", path, status);
+            progress(&status, &task);
+
+            match status {
+                TaskStatus::Done => Ok(Some(task)),
+                TaskStatus::Error | TaskStatus::Cancelled => {
+                    let comment = task.get("comment")
+                        .and_then(|c| c.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    Err(Error::TaskFailed {
+                        status: status,
+                        comment: comment,
+                    })
+                }
+                _ => Ok(None),
+            }
+        })?;
+        task.ok_or(Error::TaskTimeout(options.timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{task_status, TaskStatus, WaitOptions};
+    use super::serde_json;
+    use config::Credential;
+    use error::Error;
+    use transport::tests::stubbed;
+
+    const TASK: &'static str = "/dedicated/server/ns1/task/42";
+
+    fn options() -> WaitOptions {
+        WaitOptions {
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            timeout: Duration::from_millis(200),
+        }
+    }
+
+    #[test]
+    fn test_task_status() {
+        let task = serde_json::from_str(r#"{"id": 42, "status": "doing"}"#).unwrap();
+        assert_eq!(TaskStatus::Doing, task_status(&task));
+        assert_eq!(false, task_status(&task).is_final());
+    }
+
+    #[test]
+    fn test_task_status_from_state() {
+        let task = serde_json::from_str(r#"{"id": 42, "state": "done"}"#).unwrap();
+        assert_eq!(TaskStatus::Done, task_status(&task));
+        assert_eq!(true, task_status(&task).is_final());
+    }
+
+    #[test]
+    fn test_task_status_errors() {
+        assert_eq!(TaskStatus::Error, TaskStatus::from_api("customerError"));
+        assert_eq!(TaskStatus::Error, TaskStatus::from_api("ovhError"));
+        assert_eq!(TaskStatus::Unknown("paused".to_string()),
+                   TaskStatus::from_api("paused"));
+    }
+
+    #[test]
+    fn test_wait_for_task() {
+        let (client, api) = stubbed(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"));
+        api.on("GET", TASK, 200, r#"{"id": 42, "status": "todo"}"#);
+        api.on("GET", TASK, 200, r#"{"id": 42, "status": "doing"}"#);
+        api.on("GET", TASK, 200, r#"{"id": 42, "status": "done"}"#);
+
+        let mut seen = Vec::new();
+        let task = client.wait_for_task(TASK, &options(), |status, _| seen.push(status.clone()))
+            .unwrap();
+        assert_eq!(Some(42), task["id"].as_u64());
+        assert_eq!(vec![TaskStatus::Todo, TaskStatus::Doing, TaskStatus::Done], seen);
+        assert_eq!(3, api.sent.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_wait_for_failed_task() {
+        for &(status, ref expected) in &[("ovhError", TaskStatus::Error),
+                                     ("cancelled", TaskStatus::Cancelled)] {
+            let (client, api) =
+                stubbed(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"));
+            api.on("GET", TASK, 200, r#"{"id": 42, "status": "doing"}"#);
+            api.on("GET",
+                   TASK,
+                   200,
+                   &format!(r#"{{"id": 42, "status": "{}", "comment": "Disk failure"}}"#, status));
+            match client.wait_for_task(TASK, &options(), |_, _| {}) {
+                Err(Error::TaskFailed { status, comment }) => {
+                    assert_eq!(*expected, status);
+                    assert_eq!("Disk failure", comment);
+                }
+                other => panic!("expected a failed task, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_wait_for_task_timeout() {
+        let (client, api) = stubbed(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"));
+        api.on("GET", TASK, 200, r#"{"id": 42, "status": "doing"}"#);
+        match client.wait_for_task(TASK, &options(), |_, _| {}) {
+            Err(Error::TaskTimeout(timeout)) => assert_eq!(options().timeout, timeout),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert_eq!(true, api.sent.lock().unwrap().len() > 1);
+    }
+
+    #[test]
+    fn test_wait_for_missing_task() {
+        let (client, api) = stubbed(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"));
+        match client.wait_for_task(TASK, &options(), |_, _| {}) {
+            Err(Error::Api { status, .. }) => assert_eq!(404, status),
+            other => panic!("expected a 404, got {:?}", other),
+        }
+        assert_eq!(1, api.sent.lock().unwrap().len());
+    }
+}
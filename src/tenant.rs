@@ -0,0 +1,77 @@
+//! # Tenant
+//!
+//! Lightweight handle to call the API on behalf of one OVH account,
+//! sharing the application key, secret, middlewares and connections
+//! of an `OVHClient`.
+//!
+//! With the default `reqwest` backend, tenants share the client's
+//! connection pool. The `curl` backend has no pool: each call opens its
+//! own connection, whichever tenant it is made for.
+//!
+use client::{OVHClient, Response};
+use error::Error;
+use secret::Secret;
+
+/// Calls signed with the consumer key of a single tenant.
+#[derive(Debug)]
+pub struct Tenant<'a> {
+    client: &'a OVHClient,
+    consumer_key: Secret,
+}
+
+impl<'a> Tenant<'a> {
+    /// Sign and send a request with the tenant's consumer key.
+    pub fn execute(&self, method: &str, query: &str, body: &str) -> Result<Response, Error> {
+        self.client.execute_as(self.consumer_key.expose(), method, query, body)
+    }
+
+    pub fn client(&self) -> &'a OVHClient {
+        self.client
+    }
+}
+
+impl OVHClient {
+    /// Handle signing calls with `consumer_key` instead of the one of
+    /// the client's `Credential`.
+    pub fn tenant(&self, consumer_key: &str) -> Tenant {
+        Tenant {
+            client: self,
+            consumer_key: Secret::new(consumer_key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clock::FixedClock;
+    use config::Credential;
+    use signature::{self, Payload};
+    use transport::tests::stubbed;
+
+    #[test]
+    fn test_tenant_signs_with_its_key() {
+        let (mut ovh, stub) = stubbed(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"));
+        ovh.set_clock(FixedClock(1466716163));
+
+        ovh.tenant("tenant-ck").execute("GET", "/me", "").unwrap();
+        ovh.execute("GET", "/me", "").unwrap();
+
+        let sent = stub.sent.lock().unwrap();
+        let payload = Payload {
+            method: "GET",
+            url: "https://eu.api.ovh.com/1.0/me",
+            body: "",
+            timestamp: "1466716163",
+        };
+        assert_eq!(Some("tenant-ck"), sent[0].header("X-Ovh-Consumer"));
+        assert_eq!(Some(signature::compute(&payload, "as", "tenant-ck").as_str()),
+                   sent[0].header("X-Ovh-Signature"));
+        assert_eq!(Some("ak"), sent[0].header("X-Ovh-Application"));
+
+        // The client itself keeps its own consumer key
+        assert_eq!(Some("ck"), sent[1].header("X-Ovh-Consumer"));
+        assert_eq!(Some(signature::compute(&payload, "as", "ck").as_str()),
+                   sent[1].header("X-Ovh-Signature"));
+        assert_eq!("ck", ovh.credential.consumer_key.expose());
+    }
+}
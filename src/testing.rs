@@ -0,0 +1,402 @@
+//! # Testing
+//!
+//! In-process mock of OVH's API, enabled by the `testing` feature,
+//! to exercise code built on `OVHClient` without network access nor
+//! real credentials.
+//!
+//! The server answers `/auth/time`, checks the `X-Ovh-Signature` of
+//! every other call against its `Credential` (only the application key
+//! of `POST /auth/credential`), and serves the responses scripted for
+//! each method and path.
+//!
+//! ```rust,no_run
+//! use ovh::Credential;
+//! use ovh::testing::MockServer;
+//!
+//! let credential = Credential::new_with_credential("ovh-eu", "ak", "as", "ck");
+//! let server = MockServer::start(credential).unwrap();
+//! server.on("GET", "/me", 200, r#"{"nichandle": "xx1234-ovh"}"#);
+//!
+//! let client = server.client();
+//! let response = client.execute("GET", "/me", "").unwrap();
+//! assert_eq!(200, response.status);
+//! ```
+//!
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use client::{OVHClient, Request, Response};
+use config::Credential;
+use server::{error_response, read_request, reject, write_response};
+use signature::{self, Payload};
+
+struct Route {
+    method: String,
+    path: String,
+    responses: VecDeque<Response>,
+}
+
+struct State {
+    routes: Vec<Route>,
+    requests: Vec<Request>,
+}
+
+/// Local HTTP server mocking OVH's API, stopped when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    credential: Credential,
+    state: Arc<Mutex<State>>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start a server on a random local port, accepting calls signed with `credential`.
+    pub fn start(credential: Credential) -> io::Result<MockServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State {
+            routes: Vec::new(),
+            requests: Vec::new(),
+        }));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let handle = {
+            let credential = credential.clone();
+            let state = state.clone();
+            let running = running.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if !running.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(_) => continue,
+                    };
+                    let response = match read_request(&stream) {
+                        Ok(request) => respond(&request, &credential, &state, addr),
+                        Err(err) => reject(&err),
+                    };
+                    if let Err(err) = write_response(&stream, &response) {
+                        debug!("Mock server cannot write response: {}", err);
+                    }
+                }
+            })
+        };
+
+        Ok(MockServer {
+            addr: addr,
+            credential: credential,
+            state: state,
+            running: running,
+            handle: Some(handle),
+        })
+    }
+
+    /// API root of the server, e.g. `http://127.0.0.1:4242/1.0`.
+    pub fn url(&self) -> String {
+        format!("http://{}/1.0", self.addr)
+    }
+
+    /// A client using the server's `Credential` and pointed to it.
+    pub fn client(&self) -> OVHClient {
+        let mut client = OVHClient::with_credential(self.credential.clone());
+        client.set_base_url(&self.url());
+        client
+    }
+
+    /// Answer `method` calls to `path` (relative to `/1.0`) with `status` and `body`.
+    ///
+    /// Responses scripted for the same call are served in order, the last one
+    /// being repeated. A `path` without query string matches any query string.
+    pub fn on(&self, method: &str, path: &str, status: u16, body: &str) {
+        let response = Response {
+            status: status,
+            headers: vec![("Content-Type".to_string(),
+                           "application/json; charset=utf-8".to_string())],
+            body: body.to_string(),
+        };
+        let mut state = self.state.lock().unwrap();
+        if let Some(route) = state.routes
+            .iter_mut()
+            .find(|route| route.method == method && route.path == path) {
+            route.responses.push_back(response);
+            return;
+        }
+        let mut responses = VecDeque::new();
+        responses.push_back(response);
+        state.routes.push(Route {
+            method: method.to_string(),
+            path: path.to_string(),
+            responses: responses,
+        });
+    }
+
+    /// Calls received so far, apart from `/auth/time`, whether their
+    /// signature was valid or not. Urls are relative to the server.
+    pub fn requests(&self) -> Vec<Request> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        // Wake the accept loop up
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn respond(request: &Request,
+           credential: &Credential,
+           state: &Mutex<State>,
+           addr: SocketAddr)
+           -> Response {
+    let target = request.url.as_str();
+    if !target.starts_with("/1.0/") {
+        return error_response(404, "Not found");
+    }
+    let path = &target["/1.0".len()..];
+    if request.method == "GET" && path == "/auth/time" {
+        return Response {
+            status: 200,
+            headers: vec![],
+            body: now().to_string(),
+        };
+    }
+
+    let mut state = state.lock().unwrap();
+    state.requests.push(request.clone());
+
+    let url = format!("http://{}{}", addr, target);
+    let checked = if is_unauthenticated(&request.method, path) {
+        check_application(request, credential)
+    } else {
+        check_signature(request, &url, credential)
+    };
+    if let Err(message) = checked {
+        return error_response(400, message);
+    }
+
+    let path_only = path.split('?').next().unwrap_or("");
+    let route = state.routes.iter_mut().find(|route| {
+        route.method == request.method &&
+        (route.path == path || (!route.path.contains('?') && route.path == path_only))
+    });
+    match route {
+        Some(route) => {
+            if route.responses.len() > 1 {
+                route.responses.pop_front().unwrap()
+            } else {
+                route.responses[0].clone()
+            }
+        }
+        None => error_response(404, &format!("No mock for {} {}", request.method, path)),
+    }
+}
+
+/// Calls OVH accepts with only an application key.
+fn is_unauthenticated(method: &str, path: &str) -> bool {
+    method == "POST" && path == "/auth/credential"
+}
+
+fn check_application(request: &Request, credential: &Credential) -> Result<(), &'static str> {
+    if request.header("X-Ovh-Application") == Some(credential.application_key.as_str()) {
+        Ok(())
+    } else {
+        Err("Invalid application key")
+    }
+}
+
+/// Check a request was signed like OVH expects it.
+///
+/// The consumer key is taken from the request, so that per-tenant
+/// consumer keys are accepted.
+fn check_signature(request: &Request, url: &str, credential: &Credential) -> Result<(), &'static str> {
+    check_application(request, credential)?;
+    let timestamp = request.header("X-Ovh-Timestamp").ok_or("Missing timestamp")?;
+    let signature = request.header("X-Ovh-Signature").ok_or("Missing signature")?;
+    let consumer_key = request.header("X-Ovh-Consumer").unwrap_or("");
+    let payload = Payload {
+        method: &request.method,
+        url: url,
+        body: &request.body,
+        timestamp: timestamp,
+    };
+    if signature::verify(&payload,
+                         credential.application_secret.expose(),
+                         consumer_key,
+                         signature) {
+        Ok(())
+    } else {
+        Err("Invalid signature")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    use super::MockServer;
+    use auth::{AccessRule, PendingCredential};
+    use client::OVHClient;
+    use config::Credential;
+    use error::Error;
+    use secret::Secret;
+    use task::WaitOptions;
+
+    fn credential() -> Credential {
+        Credential::new_with_credential("ovh-eu", "ak", "as", "ck")
+    }
+
+    #[test]
+    fn test_signed_call() {
+        let server = MockServer::start(credential()).unwrap();
+        server.on("GET", "/me", 200, r#"{"nichandle":"xx1234-ovh"}"#);
+
+        let response = server.client().execute("GET", "/me", "").unwrap();
+        assert_eq!(200, response.status);
+        assert_eq!(r#"{"nichandle":"xx1234-ovh"}"#, response.body);
+        assert_eq!(1, server.requests().len());
+        assert_eq!("/1.0/me", server.requests()[0].url);
+    }
+
+    #[test]
+    fn test_bad_signature() {
+        let server = MockServer::start(credential()).unwrap();
+        server.on("GET", "/me", 200, "{}");
+
+        let other = Credential::new_with_credential("ovh-eu", "ak", "wrong", "ck");
+        let mut client = OVHClient::with_credential(other);
+        client.set_base_url(&server.url());
+        let response = client.execute("GET", "/me", "").unwrap();
+        assert_eq!(400, response.status);
+    }
+
+    #[test]
+    fn test_scripted_responses() {
+        let server = MockServer::start(credential()).unwrap();
+        server.on("POST", "/order/cart", 200, r#"{"cartId":"1"}"#);
+        server.on("POST", "/order/cart", 200, r#"{"cartId":"2"}"#);
+        let client = server.client();
+
+        let body = r#"{"ovhSubsidiary": "FR"}"#;
+        assert_eq!(r#"{"cartId":"1"}"#, client.execute("POST", "/order/cart", body).unwrap().body);
+        assert_eq!(r#"{"cartId":"2"}"#, client.execute("POST", "/order/cart", body).unwrap().body);
+        assert_eq!(r#"{"cartId":"2"}"#, client.execute("POST", "/order/cart", body).unwrap().body);
+        assert_eq!(body, server.requests()[0].body);
+    }
+
+    #[test]
+    fn test_body_too_large() {
+        let server = MockServer::start(credential()).unwrap();
+        let mut stream = TcpStream::connect(server.addr).unwrap();
+        stream.write_all(b"POST /1.0/order/cart HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(true, response.starts_with("HTTP/1.1 413 "));
+        assert_eq!(0, server.requests().len());
+    }
+
+    #[test]
+    fn test_unknown_route() {
+        let server = MockServer::start(credential()).unwrap();
+        let response = server.client().execute("GET", "/domain?whois=true", "").unwrap();
+        assert_eq!(404, response.status);
+    }
+
+    #[test]
+    fn test_consumer_key_validation() {
+        let server = MockServer::start(Credential::new_with_application("ovh-eu", "ak", "as"))
+            .unwrap();
+        server.on("POST",
+                  "/auth/credential",
+                  200,
+                  r#"{"validationUrl":"https://eu.api.ovh.com/auth/?credentialToken=t",
+                      "consumerKey":"newck","state":"pendingValidation"}"#);
+        server.on("GET", "/auth/currentCredential", 403, r#"{"message":"Invalid credential"}"#);
+        server.on("GET", "/auth/currentCredential", 200, r#"{"status":"validated"}"#);
+        let client = server.client();
+
+        let pending = client.request_consumer_key(&AccessRule::read_only(), None).unwrap();
+        assert_eq!("https://eu.api.ovh.com/auth/?credentialToken=t", pending.validation_url);
+        let options = WaitOptions {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+        };
+        let credential = client.wait_for_validation(&pending, &options).unwrap();
+        assert_eq!("newck", credential.consumer_key.expose());
+        assert_eq!("as", credential.application_secret.expose());
+        assert_eq!(None, server.requests()[0].header("X-Ovh-Signature"));
+        assert_eq!(Some("newck"), server.requests()[1].header("X-Ovh-Consumer"));
+    }
+
+    #[test]
+    fn test_consumer_key_refused() {
+        let server = MockServer::start(Credential::new_with_application("ovh-eu", "ak", "as"))
+            .unwrap();
+        server.on("GET",
+                  "/auth/currentCredential",
+                  403,
+                  r#"{"message":"This credential has been refused"}"#);
+        let pending = PendingCredential {
+            validation_url: "https://eu.api.ovh.com/auth/?credentialToken=t".to_string(),
+            consumer_key: Secret::new("newck"),
+            state: "pendingValidation".to_string(),
+        };
+
+        let options = WaitOptions {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+            timeout: Duration::from_secs(60),
+        };
+        match server.client().wait_for_validation(&pending, &options) {
+            Err(Error::Auth(message)) => assert_eq!("consumer key is refused", message),
+            other => panic!("expected a refused consumer key, got {:?}", other),
+        }
+        assert_eq!(1, server.requests().len());
+    }
+
+    #[test]
+    fn test_consumer_key_unknown_rejection() {
+        let server = MockServer::start(Credential::new_with_application("ovh-eu", "ak", "as"))
+            .unwrap();
+        server.on("GET",
+                  "/auth/currentCredential",
+                  403,
+                  r#"{"message":"This application key is invalid"}"#);
+        let pending = PendingCredential {
+            validation_url: "https://eu.api.ovh.com/auth/?credentialToken=t".to_string(),
+            consumer_key: Secret::new("newck"),
+            state: "pendingValidation".to_string(),
+        };
+
+        let options = WaitOptions {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+            timeout: Duration::from_secs(60),
+        };
+        match server.client().wait_for_validation(&pending, &options) {
+            Err(Error::Api { status, .. }) => assert_eq!(403, status),
+            other => panic!("expected a 403, got {:?}", other),
+        }
+        assert_eq!(1, server.requests().len());
+    }
+}
@@ -0,0 +1,167 @@
+//! # Trace
+//!
+//! `tracing` instrumentation, enabled by the `tracing` feature.
+//!
+//! Each call made through `OVHClient::execute` runs in an `ovh.request`
+//! span carrying the method, the path template, the status, the duration
+//! and the `X-Ovh-QueryId` returned by OVH. Query strings, bodies, headers
+//! and credentials are never recorded.
+//!
+use std::time::Duration;
+
+use tracing::Span;
+use tracing::field;
+
+use client::Response;
+use error::Error;
+
+/// Open the span of a call to `path`.
+pub fn span(method: &str, path: &str) -> Span {
+    ::tracing::info_span!("ovh.request",
+                          method = method,
+                          path = path_template(path).as_str(),
+                          status = field::Empty,
+                          duration_ms = field::Empty,
+                          query_id = field::Empty)
+}
+
+/// Record the outcome of a call on its span.
+pub fn record(span: &Span, result: &Result<Response, Error>, elapsed: Duration) {
+    let duration_ms = elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1_000_000) as u64;
+    span.record("duration_ms", &duration_ms);
+    match *result {
+        Ok(ref response) => {
+            span.record("status", &response.status);
+            if let Some(query_id) = response.header("X-Ovh-QueryId") {
+                span.record("query_id", &query_id);
+            }
+        }
+        Err(ref err) => {
+            ::tracing::warn!(parent: span, error = %err, "OVH request failed");
+        }
+    }
+}
+
+/// Replace the segments of `path` that look like identifiers with `{}`,
+/// so that spans of the same operation can be grouped.
+///
+/// Identifiers are told apart from API names by containing a digit or
+/// one of `.@:%`, e.g. `/domain/zone/example.com/record/42` gives
+/// `/domain/zone/{}/record/{}`.
+fn path_template(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or("");
+    path.split('/')
+        .map(|segment| {
+            let is_id = segment.chars()
+                .any(|c| c.is_ascii_digit() || c == '.' || c == '@' || c == ':' || c == '%');
+            if is_id { "{}" } else { segment }
+        })
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use tracing::{Event, Id, Metadata, Subscriber};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Record};
+
+    use super::{path_template, record, span};
+    use client::Response;
+    use config::Credential;
+    use transport::tests::stubbed;
+
+    /// Collects the fields recorded on spans.
+    #[derive(Clone,Default)]
+    struct Recorder(Arc<Mutex<BTreeMap<String, String>>>);
+
+    impl Visit for Recorder {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.lock().unwrap().insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.lock().unwrap().insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes) -> Id {
+            span.record(&mut self.clone());
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, values: &Record) {
+            values.record(&mut self.clone());
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event) {}
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    impl Recorder {
+        fn field(&self, name: &str) -> Option<String> {
+            self.0.lock().unwrap().get(name).cloned()
+        }
+    }
+
+    #[test]
+    fn test_path_template() {
+        assert_eq!("/domain/zone/{}/record/{}",
+                   path_template("/domain/zone/example.com/record/42"));
+        assert_eq!("/ipLoadbalancing", path_template("/ipLoadbalancing"));
+    }
+
+    #[test]
+    fn test_path_template_drops_query() {
+        assert_eq!("/domain/zone/{}/record",
+                   path_template("/domain/zone/example.com/record?subDomain=www"));
+    }
+
+    #[test]
+    fn test_request_span() {
+        let recorder = Recorder::default();
+        let (client, api) = stubbed(Credential::new_with_credential("ovh-eu", "ak", "as", "ck"));
+        api.on("GET", "/record/42?x=1", 200, "{}");
+        ::tracing::subscriber::with_default(recorder.clone(), || {
+            client.execute("GET", "/domain/zone/example.com/record/42?x=1", "").unwrap();
+        });
+
+        assert_eq!(Some("GET".to_string()), recorder.field("method"));
+        assert_eq!(Some("/domain/zone/{}/record/{}".to_string()), recorder.field("path"));
+        assert_eq!(Some("200".to_string()), recorder.field("status"));
+        assert_eq!(true, recorder.field("duration_ms").is_some());
+        assert_eq!(None, recorder.field("query_id"));
+    }
+
+    #[test]
+    fn test_record_query_id() {
+        let recorder = Recorder::default();
+        ::tracing::subscriber::with_default(recorder.clone(), || {
+            let response = Response {
+                status: 404,
+                headers: vec![("X-Ovh-QueryId".to_string(), "EU.ext-1.5f3e".to_string())],
+                body: "{}".to_string(),
+            };
+            record(&span("GET", "/me"), &Ok(response), Duration::from_millis(1500));
+        });
+
+        assert_eq!(Some("404".to_string()), recorder.field("status"));
+        assert_eq!(Some("1500".to_string()), recorder.field("duration_ms"));
+        assert_eq!(Some("EU.ext-1.5f3e".to_string()), recorder.field("query_id"));
+    }
+}
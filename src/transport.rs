@@ -0,0 +1,435 @@
+//! # Transport
+//!
+//! The last step of a call: sending a signed request and reading its
+//! response. `HttpTransport` does it over the network, other transports
+//! can record, replay or fake calls.
+//!
+#[cfg(feature = "curl")]
+use std::cell::RefCell;
+use std::io::{self, Read};
+
+#[cfg(not(feature = "curl"))]
+use reqwest;
+#[cfg(not(feature = "curl"))]
+use hyper::header::Headers;
+
+#[cfg(feature = "curl")]
+use chrono::Duration;
+#[cfg(feature = "curl")]
+use curl;
+#[cfg(feature = "curl")]
+use curl::easy::{Easy, List};
+
+use client::{Request, Response};
+use error::Error;
+
+/// Where a streamed response goes: its status and headers first, then
+/// its body as it is received.
+pub trait ResponseSink {
+    fn head(&mut self, status: u16, headers: &[(String, String)]) -> io::Result<()>;
+    fn chunk(&mut self, chunk: &[u8]) -> io::Result<()>;
+}
+
+/// Collects a streamed response, e.g. to turn it into a `Response`.
+#[derive(Debug,Clone,Default)]
+pub struct Buffer {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Buffer {
+    pub fn into_response(self) -> Result<Response, Error> {
+        Ok(Response {
+            status: self.status,
+            headers: self.headers,
+            body: String::from_utf8(self.body).map_err(|e| Error::Http(e.to_string()))?,
+        })
+    }
+}
+
+impl ResponseSink for Buffer {
+    fn head(&mut self, status: u16, headers: &[(String, String)]) -> io::Result<()> {
+        self.status = status;
+        self.headers = headers.to_vec();
+        Ok(())
+    }
+
+    fn chunk(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.body.extend_from_slice(chunk);
+        Ok(())
+    }
+}
+
+/// Hand a complete `response` to `sink`.
+pub fn deliver(response: &Response, sink: &mut dyn ResponseSink) -> Result<(), Error> {
+    sink.head(response.status, &response.headers)
+        .and_then(|_| sink.chunk(response.body.as_bytes()))
+        .map_err(|e| Error::Http(e.to_string()))
+}
+
+pub trait Transport: Send + Sync {
+    fn send(&self, request: &Request) -> Result<Response, Error>;
+
+    /// Like `send`, but hand the response to `sink` as it is received.
+    /// The returned `Response` has an empty body.
+    ///
+    /// By default, the response is fully received by `send` first.
+    fn send_streaming(&self,
+                      request: &Request,
+                      sink: &mut dyn ResponseSink)
+                      -> Result<Response, Error> {
+        let mut response = self.send(request)?;
+        deliver(&response, sink)?;
+        response.body.clear();
+        Ok(response)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for ::std::sync::Arc<T> {
+    fn send(&self, request: &Request) -> Result<Response, Error> {
+        (**self).send(request)
+    }
+
+    fn send_streaming(&self,
+                      request: &Request,
+                      sink: &mut dyn ResponseSink)
+                      -> Result<Response, Error> {
+        (**self).send_streaming(request, sink)
+    }
+}
+
+/// Send requests over HTTPS.
+#[cfg(not(feature = "curl"))]
+pub struct HttpTransport {
+    http: reqwest::Client,
+}
+
+/// Send requests over HTTPS.
+#[cfg(feature = "curl")]
+pub struct HttpTransport;
+
+impl HttpTransport {
+    #[cfg(not(feature = "curl"))]
+    pub fn new() -> HttpTransport {
+        HttpTransport { http: reqwest::Client::new().expect("Cannot create HTTP client") }
+    }
+
+    #[cfg(feature = "curl")]
+    pub fn new() -> HttpTransport {
+        curl::init();
+        HttpTransport
+    }
+}
+
+impl Transport for HttpTransport {
+    fn send(&self, request: &Request) -> Result<Response, Error> {
+        let mut buffer = Buffer::default();
+        let mut response = self.send_streaming(request, &mut buffer)?;
+        response.body = buffer.into_response()?.body;
+        if cfg!(feature = "curl") && request.method == "DELETE" {
+            //to return like API
+            response.body = "null".to_string();
+        }
+        Ok(response)
+    }
+
+    /// Send a signed request
+    /// Use Hyper client
+    #[cfg(not(feature = "curl"))]
+    fn send_streaming(&self,
+                      request: &Request,
+                      sink: &mut dyn ResponseSink)
+                      -> Result<Response, Error> {
+        let method = match request.method.as_str() {
+            "HEAD" => reqwest::Method::Head,
+            "GET" => reqwest::Method::Get,
+            "POST" => reqwest::Method::Post,
+            "PUT" => reqwest::Method::Put,
+            "PATCH" => reqwest::Method::Patch,
+            "DELETE" => reqwest::Method::Delete,
+            other => return Err(Error::Http(format!("Unsupported method {}", other))),
+        };
+        let with_body = match method {
+            reqwest::Method::Head | reqwest::Method::Get => false,
+            _ => true,
+        };
+
+        let mut headers = Headers::new();
+        for &(ref name, ref value) in &request.headers {
+            headers.set_raw(name.clone(), vec![value.clone().into_bytes()]);
+        }
+
+        // Creating an outgoing request.
+        let mut builder = self.http.request(method, &request.url).headers(headers);
+        if with_body {
+            builder = builder.body(request.body.clone());
+        }
+        let mut res = builder.send().map_err(|e| Error::Http(e.to_string()))?;
+
+        let status = res.status().to_u16();
+        let headers: Vec<(String, String)> = res.headers()
+            .iter()
+            .map(|h| (h.name().to_string(), h.value_string()))
+            .collect();
+        let io_error = |e: io::Error| Error::Http(e.to_string());
+        sink.head(status, &headers).map_err(&io_error)?;
+        let mut chunk = [0u8; 8192];
+        loop {
+            let read = res.read(&mut chunk).map_err(&io_error)?;
+            if read == 0 {
+                break;
+            }
+            sink.chunk(&chunk[..read]).map_err(&io_error)?;
+        }
+
+        Ok(Response {
+            status: status,
+            headers: headers,
+            body: String::new(),
+        })
+    }
+
+    /// Send a signed request
+    /// Use curl client
+    #[cfg(feature = "curl")]
+    fn send_streaming(&self,
+                      request: &Request,
+                      sink: &mut dyn ResponseSink)
+                      -> Result<Response, Error> {
+        let curl_error = |e: curl::Error| Error::Http(e.to_string());
+
+        //to transfer body
+        let mut upload = request.body.as_bytes();
+
+        let mut headers = List::new();
+        for &(ref name, ref value) in &request.headers {
+            headers.append(&format!("{}: {}", name, value)).map_err(&curl_error)?;
+        }
+
+        let mut client = Easy::new();
+        client.timeout(Duration::seconds(20).to_std().unwrap()).map_err(&curl_error)?;
+        client.url(&request.url).map_err(&curl_error)?;
+        client.http_headers(headers).map_err(&curl_error)?;
+
+        match request.method.as_str() {
+            "GET" => client.get(true),
+            "POST" => {
+                client.post(true)
+                    .and_then(|_| client.post_field_size(upload.len() as u64))
+            }
+            "PUT" => {
+                // Sized upload, rather than a chunked one the API may not read
+                client.upload(true)
+                    .and_then(|_| client.in_filesize(upload.len() as u64))
+            }
+            "DELETE" => {
+                client.custom_request("DELETE")
+                    .and_then(|_| client.nobody(true))
+            }
+            other => return Err(Error::Http(format!("Unsupported method {}", other))),
+        }.map_err(&curl_error)?;
+
+        // Headers of the last response received, e.g. after a `100 Continue`,
+        // handed to `sink` with the first chunk of the body
+        let head = RefCell::new((0u16, Vec::new(), false));
+        let mut sink_error = None;
+        let performed = {
+            let mut transfer = client.transfer();
+            transfer.read_function(|buf| {
+                Ok(upload.read(buf).unwrap_or(0))
+            }).map_err(&curl_error)?;
+            transfer.header_function(|line| {
+                let line = String::from_utf8_lossy(line);
+                let mut head = head.borrow_mut();
+                if line.starts_with("HTTP/") {
+                    head.0 = line.split_whitespace()
+                        .nth(1)
+                        .and_then(|status| status.parse().ok())
+                        .unwrap_or(0);
+                    head.1.clear();
+                } else if let Some(pos) = line.find(':') {
+                    head.1.push((line[..pos].trim().to_string(),
+                                 line[pos + 1..].trim().to_string()));
+                }
+                true
+            }).map_err(&curl_error)?;
+            transfer.write_function(|buf| {
+                let mut head = head.borrow_mut();
+                let mut written = if head.2 { Ok(()) } else { sink.head(head.0, &head.1) };
+                head.2 = true;
+                written = written.and_then(|_| sink.chunk(buf));
+                match written {
+                    Ok(()) => Ok(buf.len()),
+                    Err(err) => {
+                        // A short write aborts the transfer
+                        sink_error = Some(err);
+                        Ok(0)
+                    }
+                }
+            }).map_err(&curl_error)?;
+            transfer.perform()
+        };
+        if let Some(err) = sink_error {
+            return Err(Error::Http(err.to_string()));
+        }
+        performed.map_err(&curl_error)?;
+        let status = client.response_code().map_err(&curl_error)? as u16;
+        let (_, headers, started) = head.into_inner();
+        if !started {
+            sink.head(status, &headers).map_err(|e| Error::Http(e.to_string()))?;
+        }
+
+        Ok(Response {
+            status: status,
+            headers: headers,
+            body: String::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::cmp;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{ResponseSink, Transport};
+    use client::{OVHClient, Request, Response};
+    use config::Credential;
+    use error::Error;
+
+    /// Fakes OVH's API in unit tests, `testing::MockServer` without sockets.
+    ///
+    /// `/auth/time` is answered with 1466716163, other calls with the
+    /// first route of their method whose path ends their url, or a 404.
+    /// Every call but `/auth/time` is recorded in `sent`.
+    #[derive(Default)]
+    pub struct Stub {
+        routes: Mutex<Vec<Route>>,
+        pub sent: Mutex<Vec<Request>>,
+        latency: Option<Duration>,
+        /// Calls in flight, and the most seen at once.
+        in_flight: Mutex<(usize, usize)>,
+    }
+
+    struct Route {
+        method: String,
+        path: String,
+        responses: VecDeque<Response>,
+    }
+
+    impl Stub {
+        pub fn new() -> Arc<Stub> {
+            Arc::new(Stub::default())
+        }
+
+        /// Like `new`, each call taking `latency`, e.g. to check how many
+        /// are sent at once.
+        pub fn slow(latency: Duration) -> Arc<Stub> {
+            Arc::new(Stub { latency: Some(latency), ..Stub::default() })
+        }
+
+        /// Answer `method` calls whose url ends with `path` with `status` and `body`.
+        ///
+        /// Responses scripted for the same call are served in order, the last one
+        /// being repeated.
+        pub fn on(&self, method: &str, path: &str, status: u16, body: &str) {
+            let response = Response {
+                status: status,
+                headers: vec![],
+                body: body.to_string(),
+            };
+            let mut routes = self.routes.lock().unwrap();
+            if let Some(route) = routes.iter_mut()
+                .find(|route| route.method == method && route.path == path) {
+                route.responses.push_back(response);
+                return;
+            }
+            let mut responses = VecDeque::new();
+            responses.push_back(response);
+            routes.push(Route {
+                method: method.to_string(),
+                path: path.to_string(),
+                responses: responses,
+            });
+        }
+
+        /// Most calls that were in flight at once.
+        pub fn max_in_flight(&self) -> usize {
+            self.in_flight.lock().unwrap().1
+        }
+
+        fn answer(&self, request: &Request) -> Response {
+            let mut routes = self.routes.lock().unwrap();
+            let route = routes.iter_mut()
+                .find(|route| route.method == request.method && request.url.ends_with(&route.path));
+            match route {
+                Some(route) => {
+                    if route.responses.len() > 1 {
+                        route.responses.pop_front().unwrap()
+                    } else {
+                        route.responses[0].clone()
+                    }
+                }
+                None => {
+                    Response {
+                        status: 404,
+                        headers: vec![],
+                        body: r#"{"message": "not found"}"#.to_string(),
+                    }
+                }
+            }
+        }
+    }
+
+    impl Transport for Stub {
+        fn send(&self, request: &Request) -> Result<Response, Error> {
+            if request.url.ends_with("/auth/time") {
+                return Ok(Response {
+                    status: 200,
+                    headers: vec![],
+                    body: "1466716163".to_string(),
+                });
+            }
+            self.sent.lock().unwrap().push(request.clone());
+            if let Some(latency) = self.latency {
+                {
+                    let mut in_flight = self.in_flight.lock().unwrap();
+                    in_flight.0 += 1;
+                    in_flight.1 = cmp::max(in_flight.0, in_flight.1);
+                }
+                thread::sleep(latency);
+                self.in_flight.lock().unwrap().0 -= 1;
+            }
+            Ok(self.answer(request))
+        }
+
+        /// Streams the body in two chunks.
+        fn send_streaming(&self,
+                          request: &Request,
+                          sink: &mut dyn ResponseSink)
+                          -> Result<Response, Error> {
+            let mut response = self.send(request)?;
+            {
+                let (first, second) = response.body.as_bytes().split_at(response.body.len() / 2);
+                sink.head(response.status, &response.headers)
+                    .and_then(|_| sink.chunk(first))
+                    .and_then(|_| sink.chunk(second))
+                    .map_err(|e| Error::Http(e.to_string()))?;
+            }
+            response.body.clear();
+            Ok(response)
+        }
+    }
+
+    /// A client signing with `credential`, whose calls are answered by a `Stub`.
+    pub fn stubbed(credential: Credential) -> (OVHClient, Arc<Stub>) {
+        let stub = Stub::new();
+        let mut client = OVHClient::with_credential(credential);
+        client.set_transport(stub.clone());
+        (client, stub)
+    }
+}
@@ -0,0 +1,366 @@
+//! # Validate
+//!
+//! Check calls against API schemas before they are signed and sent, so
+//! that a malformed call fails locally with a precise error instead of
+//! a round-trip and a vague 400.
+//!
+//! ```rust,no_run
+//! use ovh::OVHClient;
+//!
+//! let mut client = OVHClient::new();
+//! client.enable_validation(&["domain"]).unwrap();
+//! // Error::Validation: missing required body parameter target
+//! let result = client.execute("POST", "/domain/zone/example.com/record", r#"{"fieldType": "A"}"#);
+//! ```
+//!
+//! Calls to paths covered by none of the schemas are not checked.
+//!
+extern crate serde_json;
+
+use std::str;
+
+use self::serde_json::Value;
+
+use client::OVHClient;
+use error::Error;
+use schema::{Lookup, ParamType, Parameter, Schema, STRING_TYPES};
+
+/// Checks calls against a set of schemas, e.g. those of `/domain` and `/me`.
+#[derive(Debug,Clone)]
+pub struct Validator {
+    pub schemas: Vec<Schema>,
+}
+
+/// Decode a percent-encoded query string component or path.
+pub(crate) fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Split a query string into decoded `(name, value)` pairs.
+fn query_params(query: &str) -> Vec<(String, String)> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            let name = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("");
+            (decode(name), decode(value))
+        })
+        .collect()
+}
+
+/// Type of a parameter, e.g. `long[]` rather than `long` for a list.
+fn param_type(param: &Parameter) -> &str {
+    param.full_type.as_ref().unwrap_or(&param.data_type)
+}
+
+fn is_integer(data_type: &str) -> bool {
+    data_type == "long" || data_type == "int" || data_type == "integer"
+}
+
+fn is_float(data_type: &str) -> bool {
+    data_type == "double" || data_type == "float"
+}
+
+/// Check a JSON `value` against `data_type`, `at` naming it in errors.
+///
+/// Types found neither among the primitives nor in the schema's models,
+/// e.g. generic models, are accepted as is.
+fn check_value(schema: &Schema, data_type: &str, value: &Value, at: &str) -> Result<(), String> {
+    if data_type.ends_with("[]") {
+        let item_type = &data_type[..data_type.len() - 2];
+        return match *value {
+            Value::Array(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    check_value(schema, item_type, item, &format!("{}[{}]", at, i))?;
+                }
+                Ok(())
+            }
+            _ => Err(format!("{} must be a list of {}", at, item_type)),
+        };
+    }
+
+    let valid = if is_integer(data_type) {
+        value.is_i64() || value.is_u64()
+    } else if is_float(data_type) {
+        value.is_number()
+    } else if data_type == "boolean" {
+        value.is_boolean()
+    } else if STRING_TYPES.contains(&data_type) {
+        value.is_string()
+    } else if let Some(model) = schema.model(data_type) {
+        if let Some(ref values) = model.enum_values {
+            return match value.as_str() {
+                Some(text) if values.iter().any(|v| v == text) => Ok(()),
+                _ => Err(format!("{} must be one of {}, got {}", at, values.join(", "), value)),
+            };
+        }
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => return Err(format!("{} must be a {} object", at, data_type)),
+        };
+        for (name, property) in &model.properties {
+            let kind = property.full_type.as_ref().unwrap_or(&property.kind);
+            match object.get(name) {
+                Some(&Value::Null) | None => {}
+                Some(field) => check_value(schema, kind, field, &format!("{}.{}", at, name))?,
+            }
+        }
+        true
+    } else {
+        true
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("{} must be a {}, got {}", at, data_type, value))
+    }
+}
+
+/// Check a path or query parameter, always given as text.
+fn check_text(schema: &Schema, data_type: &str, text: &str, at: &str) -> Result<(), String> {
+    let valid = if is_integer(data_type) {
+        text.parse::<i64>().is_ok()
+    } else if is_float(data_type) {
+        text.parse::<f64>().is_ok()
+    } else if data_type == "boolean" {
+        text == "true" || text == "false"
+    } else if let Some(values) = schema.model(data_type).and_then(|m| m.enum_values.as_ref()) {
+        if !values.iter().any(|v| v == text) {
+            return Err(format!("{} must be one of {}, got {:?}", at, values.join(", "), text));
+        }
+        true
+    } else {
+        true
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("{} must be a {}, got {:?}", at, data_type, text))
+    }
+}
+
+/// Check the parameters of a call to the operation `found`.
+fn check_call(schema: &Schema, found: &Lookup, query: &str, body: &str) -> Result<(), String> {
+    let operation = found.operation;
+
+    for param in operation.parameters_in(ParamType::Path) {
+        let name = param.name.as_ref().map(|n| n.as_str()).unwrap_or("");
+        if let Some(&(_, ref value)) = found.path_params.iter().find(|&&(ref n, _)| n == name) {
+            check_text(schema,
+                       param_type(param),
+                       &decode(value),
+                       &format!("path parameter {}", name))?;
+        }
+    }
+
+    let given = query_params(query);
+    for param in operation.parameters_in(ParamType::Query) {
+        let name = param.name.as_ref().map(|n| n.as_str()).unwrap_or("");
+        match given.iter().find(|&&(ref n, _)| n == name) {
+            Some(&(_, ref value)) => {
+                check_text(schema,
+                           param_type(param),
+                           value,
+                           &format!("query parameter {}", name))?
+            }
+            None if param.required => {
+                return Err(format!("missing required query parameter {}", name))
+            }
+            None => {}
+        }
+    }
+
+    let params = operation.parameters_in(ParamType::Body);
+    let body: Value = if body.trim().is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(body).map_err(|err| format!("body is not JSON: {}", err))?
+    };
+
+    // A parameter without name stands for the whole body
+    if let Some(whole) = params.iter().find(|param| param.name.is_none()) {
+        return match body {
+            Value::Null if whole.required => Err("missing required body".to_string()),
+            Value::Null => Ok(()),
+            ref value => check_value(schema, param_type(whole), value, "body"),
+        };
+    }
+    if params.is_empty() {
+        return Ok(());
+    }
+
+    let empty = serde_json::Map::new();
+    let object = match body {
+        Value::Object(ref object) => object,
+        Value::Null => &empty,
+        _ => return Err("body must be a JSON object".to_string()),
+    };
+    for param in params {
+        let name = param.name.as_ref().map(|n| n.as_str()).unwrap_or("");
+        match object.get(name) {
+            Some(&Value::Null) | None if param.required => {
+                return Err(format!("missing required body parameter {}", name))
+            }
+            Some(&Value::Null) | None => {}
+            Some(value) => {
+                check_value(schema,
+                            param_type(param),
+                            value,
+                            &format!("body parameter {}", name))?
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Validator {
+    pub fn new(schemas: Vec<Schema>) -> Validator {
+        Validator { schemas: schemas }
+    }
+
+    /// Schema covering `path`, the one with the longest resource path
+    /// if several do, e.g. `/domain/zone` rather than `/domain`.
+    pub fn schema_for(&self, path: &str) -> Option<&Schema> {
+        let path = path.split('?').next().unwrap_or("");
+        self.schemas
+            .iter()
+            .filter(|schema| {
+                let prefix = schema.resource_path.trim_end_matches('/');
+                path == prefix || path.starts_with(&format!("{}/", prefix))
+            })
+            .max_by_key(|schema| schema.resource_path.len())
+    }
+
+    /// Check a `method` call to `query`, e.g. `/domain/zone?x=1`, with `body`.
+    ///
+    /// Returns `Error::Validation` when the call has no matching operation,
+    /// misses a required parameter, or a parameter has the wrong type or
+    /// is not one of the values of its enum.
+    pub fn validate(&self, method: &str, query: &str, body: &str) -> Result<(), Error> {
+        let schema = match self.schema_for(query) {
+            Some(schema) => schema,
+            None => return Ok(()),
+        };
+        let found = match schema.operation(method, query) {
+            Some(found) => found,
+            None => {
+                return Err(Error::Validation(format!("{} {} matches no operation of {}",
+                                                     method,
+                                                     query.split('?').next().unwrap_or(""),
+                                                     schema.resource_path)))
+            }
+        };
+        let params = query.splitn(2, '?').nth(1).unwrap_or("");
+        check_call(schema, &found, params, body)
+            .map_err(|err| Error::Validation(format!("{} {}: {}", method, found.api.path, err)))
+    }
+}
+
+impl OVHClient {
+    /// Download the schemas of `apis`, e.g. `domain` or `me`, and check
+    /// calls against them from now on, see `set_validator`.
+    pub fn enable_validation(&mut self, apis: &[&str]) -> Result<(), Error> {
+        let mut schemas = Vec::new();
+        for api in apis {
+            schemas.push(self.fetch_schema(api)?);
+        }
+        self.set_validator(Some(Validator::new(schemas)));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, Validator};
+    use error::Error;
+    use schema::Schema;
+    use schema::tests::DOMAIN;
+
+    fn validator() -> Validator {
+        Validator::new(vec![Schema::parse(DOMAIN).unwrap()])
+    }
+
+    fn message(result: Result<(), Error>) -> String {
+        match result {
+            Err(Error::Validation(message)) => message,
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!("ex/a.com", decode("ex%2Fa.com"));
+        assert_eq!("100%", decode("100%"));
+        // Not a char boundary after the `%`
+        assert_eq!("%aé", decode("%aé"));
+        assert_eq!("%é", decode("%é"));
+    }
+
+    #[test]
+    fn test_multibyte_query() {
+        let validator = validator();
+        let result = validator.validate("GET", "/domain/zone/example.com/record?subDomain=%aé", "");
+        assert_eq!(true, result.is_ok());
+        let result = validator.validate("GET", "/domain/zone/ex%é.com/record", "");
+        assert_eq!(true, result.is_ok());
+    }
+
+    #[test]
+    fn test_valid_calls() {
+        let validator = validator();
+        let record = r#"{"fieldType": "A", "target": "1.2.3.4", "ttl": 60, "subDomain": null}"#;
+        assert_eq!(true, validator.validate("POST", "/domain/zone/example.com/record", record)
+                             .is_ok());
+        assert_eq!(true, validator.validate("GET", "/domain/zone/example.com/record?fieldType=MX", "")
+                             .is_ok());
+        assert_eq!(true, validator.validate("DELETE", "/domain/zone/example.com/record/42", "")
+                             .is_ok());
+        // Not covered by any schema
+        assert_eq!(true, validator.validate("GET", "/me", "").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_calls() {
+        let validator = validator();
+        let zone = "/domain/zone/example.com/record";
+        assert_eq!("PUT /domain/zone/example.com/record matches no operation of /domain",
+                   message(validator.validate("PUT", zone, "{}")));
+        assert_eq!("POST /domain/zone/{zoneName}/record: missing required body parameter target",
+                   message(validator.validate("POST", zone, r#"{"fieldType": "A"}"#)));
+        assert_eq!(true, message(validator.validate("POST", zone, r#"{"fieldType": "AA", "target": "x"}"#))
+                             .contains("body parameter fieldType must be one of A, AAAA"));
+        assert_eq!(true, message(validator.validate("POST", zone, r#"{"fieldType": "A", "target": "x", "ttl": "60"}"#))
+                             .ends_with("body parameter ttl must be a long, got \"60\""));
+        assert_eq!(true, message(validator.validate("GET", &format!("{}?fieldType=SPF", zone), ""))
+                             .contains("query parameter fieldType must be one of"));
+        assert_eq!(true, message(validator.validate("GET", &format!("{}/abc", zone), ""))
+                             .contains("path parameter id must be a long"));
+        assert_eq!(true, message(validator.validate("POST", zone, "[1]"))
+                             .ends_with("body must be a JSON object"));
+    }
+}
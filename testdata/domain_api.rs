@@ -0,0 +1,110 @@
+// Generated by ovh-codegen from the schema of /domain, do not edit.
+
+/// Zone resource records
+#[derive(Debug,Clone,Serialize,Deserialize)]
+pub struct DomainZoneRecord {
+    /// Resource record Name
+    /// Read only.
+    #[serde(rename = "fieldType")]
+    pub field_type: ZoneNamedResolutionFieldTypeEnum,
+    /// Id of the zone resource record
+    /// Read only.
+    pub id: i64,
+    /// Resource record subdomain
+    #[serde(rename = "subDomain")]
+    pub sub_domain: Option<String>,
+    /// Resource record target
+    pub target: String,
+    /// Resource record ttl
+    pub ttl: Option<i64>,
+    /// Resource record zone
+    /// Read only.
+    pub zone: String,
+}
+
+/// Resource record fieldType
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,Serialize,Deserialize)]
+pub enum ZoneNamedResolutionFieldTypeEnum {
+    #[serde(rename = "A")]
+    A,
+    #[serde(rename = "AAAA")]
+    Aaaa,
+    #[serde(rename = "CNAME")]
+    Cname,
+    #[serde(rename = "MX")]
+    Mx,
+    #[serde(rename = "NS")]
+    Ns,
+    #[serde(rename = "TXT")]
+    Txt,
+}
+
+/// Operations of `/domain`.
+pub trait DomainApi {
+    fn ovh_client(&self) -> &::OVHClient;
+
+    /// Records of the zone
+    ///
+    /// `GET /domain/zone/{zoneName}/record`
+    fn get_domain_zone_zone_name_record(&self, zone_name: &str, field_type: Option<ZoneNamedResolutionFieldTypeEnum>, sub_domain: Option<&str>) -> Result<Vec<i64>, ::Error> {
+        let path = format!("/domain/zone/{}/record{}", ::codegen::encode(&::codegen::param(&zone_name)), ::codegen::query(&[("fieldType", field_type.map(|v| ::codegen::param(&v))), ("subDomain", sub_domain.map(|v| ::codegen::param(&v)))]));
+        let body = String::new();
+        let response = self.ovh_client().execute("GET", &path, &body)?;
+        ::codegen::decode(response)
+    }
+
+    /// Create a new DNS record
+    ///
+    /// `POST /domain/zone/{zoneName}/record`
+    fn post_domain_zone_zone_name_record(&self, zone_name: &str, field_type: ZoneNamedResolutionFieldTypeEnum, target: &str, sub_domain: Option<&str>, ttl: Option<i64>) -> Result<DomainZoneRecord, ::Error> {
+        let path = format!("/domain/zone/{}/record{}", ::codegen::encode(&::codegen::param(&zone_name)), ::codegen::query(&[]));
+        let mut request_body = ::codegen::Body::new();
+        request_body.set("fieldType", &field_type)?;
+        request_body.set("target", &target)?;
+        if let Some(value) = sub_domain {
+            request_body.set("subDomain", &value)?;
+        }
+        if let Some(value) = ttl {
+            request_body.set("ttl", &value)?;
+        }
+        let body = request_body.to_json();
+        let response = self.ovh_client().execute("POST", &path, &body)?;
+        ::codegen::decode(response)
+    }
+
+    /// Get this object properties
+    ///
+    /// `GET /domain/zone/{zoneName}/record/{id}`
+    fn get_domain_zone_zone_name_record_id(&self, zone_name: &str, id: i64) -> Result<DomainZoneRecord, ::Error> {
+        let path = format!("/domain/zone/{}/record/{}{}", ::codegen::encode(&::codegen::param(&zone_name)), ::codegen::encode(&::codegen::param(&id)), ::codegen::query(&[]));
+        let body = String::new();
+        let response = self.ovh_client().execute("GET", &path, &body)?;
+        ::codegen::decode(response)
+    }
+
+    /// Delete a DNS record
+    ///
+    /// `DELETE /domain/zone/{zoneName}/record/{id}`
+    fn delete_domain_zone_zone_name_record_id(&self, zone_name: &str, id: i64) -> Result<(), ::Error> {
+        let path = format!("/domain/zone/{}/record/{}{}", ::codegen::encode(&::codegen::param(&zone_name)), ::codegen::encode(&::codegen::param(&id)), ::codegen::query(&[]));
+        let body = String::new();
+        let response = self.ovh_client().execute("DELETE", &path, &body)?;
+        ::codegen::decode(response)
+    }
+
+    /// Export zone
+    ///
+    /// `GET /domain/zone/{zoneName}/record/export`
+    fn get_domain_zone_zone_name_record_export(&self, zone_name: &str) -> Result<String, ::Error> {
+        let path = format!("/domain/zone/{}/record/export{}", ::codegen::encode(&::codegen::param(&zone_name)), ::codegen::query(&[]));
+        let body = String::new();
+        let response = self.ovh_client().execute("GET", &path, &body)?;
+        ::codegen::decode(response)
+    }
+}
+
+impl DomainApi for ::OVHClient {
+    fn ovh_client(&self) -> &::OVHClient {
+        self
+    }
+}